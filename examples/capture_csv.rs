@@ -0,0 +1,29 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use ydlidar_sdk::builder::{LidarBuilder, Model};
+
+/*
+    Captures 10 scans from a G2 and writes each one to scan_NN.csv in the working directory.
+    Usage: cargo run --example capture_csv [serial port]
+*/
+fn main() -> Result<(), Box<dyn Error>> {
+    let port = std::env::args().nth(1).unwrap_or_else(|| "/dev/ydlidar".to_string());
+
+    let mut lidar = LidarBuilder::for_model(Model::G2)
+        .serial_port(&port)
+        .build()?;
+
+    lidar.initialize()?;
+    lidar.turn_on()?;
+
+    for (i, scan) in lidar.scans().take(10).enumerate() {
+        let file = File::create(format!("scan_{:02}.csv", i))?;
+        scan?.write_csv(BufWriter::new(file))?;
+    }
+
+    lidar.turn_off()?;
+    lidar.disconnect();
+
+    Ok(())
+}
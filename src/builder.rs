@@ -0,0 +1,367 @@
+use std::path::Path;
+use crate::capabilities::DeviceCapabilities;
+use crate::config::LidarConfig;
+use crate::ydlidar::{ignore_ranges, DeviceConnectionType, ErrorKind, LidarError, LidarModelType, ScanFrequency, Ydlidar};
+
+/// Common YDLidar units with known-good default settings.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Model {
+    X2,
+    G2,
+    TG15,
+}
+
+impl Model {
+    /// Sample rates (in kHz) accepted by the `SampleRate` property on this model. The SDK has no way
+    /// to query them from the device.
+    pub fn supported_sample_rates(&self) -> &'static [i32] {
+        match self {
+            Model::X2 => &[3],
+            Model::G2 => &[5],
+            Model::TG15 => &[10, 18, 20],
+        }
+    }
+
+    /// Features of each model. TG15 covers its Ethernet variant too, which is set up with `LidarBuilder::tcp`.
+    pub fn capabilities(&self) -> DeviceCapabilities {
+        match self {
+            Model::X2 => DeviceCapabilities { motor_control: true, ..DeviceCapabilities::default() },
+            Model::G2 => DeviceCapabilities { intensity: true, motor_control: true, ..DeviceCapabilities::default() },
+            Model::TG15 => DeviceCapabilities { network: true, ..DeviceCapabilities::default() },
+        }
+    }
+}
+
+/// Accumulates lidar settings and applies them all at once in `build`.
+#[derive(Clone, Debug, Default)]
+pub struct LidarBuilder {
+    config: LidarConfig,
+    model: Option<Model>,
+    phase_lock: bool,
+    max_points: Option<usize>,
+    skip_invalid: bool,
+    error: Option<String>,
+}
+
+impl LidarBuilder {
+    /// Unlike `for_model`, declares no capabilities, so no property is rejected as `Unsupported`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pre-fills baud rate, lidar type, sample rate and angle/range limits for `model`.
+    pub fn for_model(model: Model) -> Self {
+        let config = match model {
+            Model::X2 => LidarConfig {
+                baud_rate: Some(115200),
+                lidar_type: Some(LidarModelType::Triangle),
+                device_type: Some(DeviceConnectionType::Serial),
+                sample_rate: Some(3),
+                max_range: Some(8.0),
+                min_range: Some(0.1),
+                max_angle: Some(180.0),
+                min_angle: Some(-180.0),
+                scan_frequency: Some(7.0),
+                single_channel: Some(true),
+                intensity: Some(false),
+                support_motor_dtr_ctrl: Some(true),
+                ..LidarConfig::default()
+            },
+            Model::G2 => LidarConfig {
+                baud_rate: Some(230400),
+                lidar_type: Some(LidarModelType::Triangle),
+                device_type: Some(DeviceConnectionType::Serial),
+                sample_rate: Some(5),
+                max_range: Some(16.0),
+                min_range: Some(0.28),
+                max_angle: Some(180.0),
+                min_angle: Some(-180.0),
+                scan_frequency: Some(10.0),
+                single_channel: Some(false),
+                intensity: Some(true),
+                intensity_bit: Some(10),
+                support_motor_dtr_ctrl: Some(true),
+                ..LidarConfig::default()
+            },
+            Model::TG15 => LidarConfig {
+                baud_rate: Some(512000),
+                lidar_type: Some(LidarModelType::Tof),
+                device_type: Some(DeviceConnectionType::Serial),
+                sample_rate: Some(20),
+                max_range: Some(30.0),
+                min_range: Some(0.05),
+                max_angle: Some(180.0),
+                min_angle: Some(-180.0),
+                scan_frequency: Some(10.0),
+                single_channel: Some(false),
+                intensity: Some(false),
+                support_motor_dtr_ctrl: Some(false),
+                ..LidarConfig::default()
+            },
+        };
+
+        Self {
+            config,
+            model: Some(model),
+            ..Self::default()
+        }
+    }
+
+    pub fn serial_port(mut self, port: &str) -> Self {
+        self.config.serial_port = Some(port.into());
+        self
+    }
+
+    pub fn serial_port_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.config.serial_port = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn ignore_array(mut self, array: &str) -> Self {
+        self.config.ignore_array = Some(array.to_string());
+        self
+    }
+
+    /// Typed alternative to `ignore_array`. Invalid ranges are reported by `build`.
+    pub fn ignore_ranges(mut self, pairs: &[(f32, f32)]) -> Self {
+        match ignore_ranges(pairs) {
+            Ok(array) => self.config.ignore_array = Some(array),
+            Err(e) => self.error = self.error.or(Some(e.description)),
+        }
+        self
+    }
+
+    /// Connects to a network lidar (e.g. the Ethernet TG models) at `ip`:`port` over TCP.
+    pub fn tcp(mut self, ip: &str, port: i32) -> Self {
+        self.config.serial_port = Some(ip.into());
+        self.config.baud_rate = Some(port);
+        self.config.device_type = Some(DeviceConnectionType::Tcp);
+        self
+    }
+
+    pub fn baud_rate(mut self, baud_rate: i32) -> Self {
+        self.config.baud_rate = Some(baud_rate);
+        self
+    }
+
+    pub fn lidar_type(mut self, lidar_type: LidarModelType) -> Self {
+        self.config.lidar_type = Some(lidar_type);
+        self
+    }
+
+    pub fn device_type(mut self, device_type: DeviceConnectionType) -> Self {
+        self.config.device_type = Some(device_type);
+        self
+    }
+
+    /// For builders created with `for_model`, a rate the model doesn't support is reported by `build`.
+    pub fn sample_rate(mut self, sample_rate: i32) -> Self {
+        match self.model {
+            Some(model) if !model.supported_sample_rates().contains(&sample_rate) => {
+                let e = format!("sample rate {} is not supported by {:?} (supported: {:?})", sample_rate, model, model.supported_sample_rates());
+                self.error = self.error.or(Some(e));
+            }
+            _ => self.config.sample_rate = Some(sample_rate),
+        }
+        self
+    }
+
+    pub fn abnormal_check_count(mut self, count: i32) -> Self {
+        self.config.abnormal_check_count = Some(count);
+        self
+    }
+
+    pub fn intensity_bit(mut self, bits: i32) -> Self {
+        self.config.intensity_bit = Some(bits);
+        self
+    }
+
+    pub fn max_range(mut self, range: f32) -> Self {
+        self.config.max_range = Some(range);
+        self
+    }
+
+    pub fn min_range(mut self, range: f32) -> Self {
+        self.config.min_range = Some(range);
+        self
+    }
+
+    pub fn max_angle(mut self, angle: f32) -> Self {
+        self.config.max_angle = Some(angle);
+        self
+    }
+
+    pub fn min_angle(mut self, angle: f32) -> Self {
+        self.config.min_angle = Some(angle);
+        self
+    }
+
+    /// An invalid frequency is reported by `build`.
+    pub fn scan_frequency(mut self, hz: f32) -> Self {
+        match ScanFrequency::new(hz) {
+            Ok(freq) => self.config.scan_frequency = Some(freq.hz()),
+            Err(e) => self.error = self.error.or(Some(e.description)),
+        }
+        self
+    }
+
+    pub fn fixed_resolution(mut self, enabled: bool) -> Self {
+        self.config.fixed_resolution = Some(enabled);
+        self
+    }
+
+    pub fn reversion(mut self, enabled: bool) -> Self {
+        self.config.reversion = Some(enabled);
+        self
+    }
+
+    pub fn inverted(mut self, enabled: bool) -> Self {
+        self.config.inverted = Some(enabled);
+        self
+    }
+
+    pub fn auto_reconnect(mut self, enabled: bool) -> Self {
+        self.config.auto_reconnect = Some(enabled);
+        self
+    }
+
+    pub fn single_channel(mut self, enabled: bool) -> Self {
+        self.config.single_channel = Some(enabled);
+        self
+    }
+
+    pub fn intensity(mut self, enabled: bool) -> Self {
+        self.config.intensity = Some(enabled);
+        self
+    }
+
+    pub fn support_motor_dtr_ctrl(mut self, enabled: bool) -> Self {
+        self.config.support_motor_dtr_ctrl = Some(enabled);
+        self
+    }
+
+    pub fn support_heart_beat(mut self, enabled: bool) -> Self {
+        self.config.support_heart_beat = Some(enabled);
+        self
+    }
+
+    /// See `Ydlidar::set_phase_lock`.
+    pub fn phase_lock(mut self, enabled: bool) -> Self {
+        self.phase_lock = enabled;
+        self
+    }
+
+    pub fn max_points(mut self, max_points: usize) -> Self {
+        self.max_points = Some(max_points);
+        self
+    }
+
+    pub fn skip_invalid(mut self, enabled: bool) -> Self {
+        self.skip_invalid = enabled;
+        self
+    }
+
+    /// Checks the settings for consistency (see `Ydlidar::initialize`), then creates the lidar and applies every
+    /// configured property in order, stopping at the first error. For builders created with `for_model`, settings
+    /// the model lacks the hardware for (see `Model::capabilities`) fail with `ErrorKind::Unsupported`.
+    pub fn build(self) -> Result<Ydlidar, LidarError> {
+        if let Some(e) = self.error {
+            return Err(LidarError::with_kind(ErrorKind::InvalidValue, &e));
+        }
+        self.config.check_consistency()?;
+
+        let mut lidar = Ydlidar::new();
+        if let Some(model) = self.model {
+            lidar.set_capabilities(model.capabilities());
+        }
+        lidar.apply_config(&self.config)?;
+        lidar.set_phase_lock(self.phase_lock);
+        lidar.set_skip_invalid(self.skip_invalid);
+        if let Some(max_points) = self.max_points {
+            lidar.set_max_points(max_points);
+        }
+
+        Ok(lidar)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn for_model_can_be_overridden() {
+        let lidar = LidarBuilder::for_model(Model::X2)
+            .serial_port("/dev/ttyUSB0")
+            .scan_frequency(5.0)
+            .build()
+            .unwrap();
+
+        let config = lidar.dump_config();
+        assert_eq!(Some(PathBuf::from("/dev/ttyUSB0")), config.serial_port);
+        assert_eq!(Some(115200), config.baud_rate);
+        assert_eq!(Some(5.0), config.scan_frequency);
+    }
+
+    #[test]
+    fn build_reports_invalid_scan_frequency() {
+        let result = LidarBuilder::new().scan_frequency(f32::NAN).build();
+
+        assert_eq!(ErrorKind::InvalidValue, result.err().unwrap().kind);
+    }
+
+    #[test]
+    fn build_reports_invalid_ignore_ranges() {
+        let result = LidarBuilder::new().ignore_ranges(&[(30.0, -30.0)]).build();
+
+        assert_eq!(ErrorKind::InvalidValue, result.err().unwrap().kind);
+    }
+
+    #[test]
+    fn build_reports_unsupported_sample_rate_for_model() {
+        let result = LidarBuilder::for_model(Model::G2).sample_rate(9).build();
+
+        assert_eq!(ErrorKind::InvalidValue, result.err().unwrap().kind);
+    }
+
+    #[test]
+    fn model_presets_use_supported_sample_rates() {
+        for model in [Model::X2, Model::G2, Model::TG15] {
+            let rate = LidarBuilder::for_model(model).config.sample_rate.unwrap();
+            assert!(model.supported_sample_rates().contains(&rate), "{:?}", model);
+        }
+    }
+
+    #[test]
+    fn tcp_sets_endpoint_and_device_type() {
+        let config = LidarBuilder::new().tcp("192.168.0.11", 8000).build().unwrap().dump_config();
+
+        assert_eq!(Some(PathBuf::from("192.168.0.11")), config.serial_port);
+        assert_eq!(Some(8000), config.baud_rate);
+        assert_eq!(Some(DeviceConnectionType::Tcp), config.device_type);
+    }
+
+    #[test]
+    fn build_reports_intensity_without_bit() {
+        let result = LidarBuilder::for_model(Model::X2).intensity(true).build();
+
+        assert_eq!(ErrorKind::InvalidValue, result.err().unwrap().kind);
+    }
+
+    #[test]
+    fn build_rejects_features_the_model_lacks() {
+        let result = LidarBuilder::for_model(Model::X2).intensity(true).intensity_bit(8).build();
+        assert_eq!(ErrorKind::Unsupported, result.err().unwrap().kind);
+
+        let result = LidarBuilder::for_model(Model::X2).tcp("192.168.0.11", 8000).build();
+        assert_eq!(ErrorKind::Unsupported, result.err().unwrap().kind);
+    }
+
+    #[test]
+    fn tg15_preset_accepts_a_network_endpoint() {
+        let lidar = LidarBuilder::for_model(Model::TG15).tcp("192.168.0.11", 8000).build().unwrap();
+
+        assert_eq!(Some(DeviceConnectionType::Tcp), lidar.dump_config().device_type);
+    }
+}
@@ -0,0 +1,104 @@
+use crate::config::LidarConfig;
+use crate::ydlidar::{DeviceConnectionType, ErrorKind, LidarError, LidarModelType, LidarProperty};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Capability {
+    Intensity,
+    MotorControl,
+    HeartBeat,
+    Network,
+}
+
+impl Capability {
+    /// The capability a property needs to take effect, if any. Disabling a feature never needs one.
+    pub fn required_by(prop: &LidarProperty) -> Option<Capability> {
+        match *prop {
+            LidarProperty::Intensity(true) | LidarProperty::IntensityBit(_) => Some(Capability::Intensity),
+            LidarProperty::SupportMotorDtrCtrl(true) => Some(Capability::MotorControl),
+            LidarProperty::SupportHeartBeat(true) => Some(Capability::HeartBeat),
+            LidarProperty::NetworkAddress(_) | LidarProperty::NetworkPort(_) => Some(Capability::Network),
+            LidarProperty::DeviceType(DeviceConnectionType::Tcp | DeviceConnectionType::Udp) => Some(Capability::Network),
+            LidarProperty::LidarType(LidarModelType::TofNet) => Some(Capability::Network),
+            _ => None,
+        }
+    }
+}
+
+/// Features available on a unit. The SDK can't report them, so they have to be declared with
+/// `Ydlidar::set_capabilities` (`LidarBuilder::for_model` does so for its model); from then on, properties that
+/// need a missing feature are rejected with `ErrorKind::Unsupported` instead of being passed to the SDK.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DeviceCapabilities {
+    pub intensity: bool,
+    pub motor_control: bool,
+    pub heart_beat: bool,
+    pub network: bool,
+}
+
+impl DeviceCapabilities {
+    /// Every feature present. This is what a `Ydlidar` assumes until its capabilities are declared.
+    pub fn all() -> Self {
+        Self {
+            intensity: true,
+            motor_control: true,
+            heart_beat: true,
+            network: true,
+        }
+    }
+
+    /// The features the properties in `config` make use of. This describes the configuration, not the unit,
+    /// so it can't be used to check the configuration itself.
+    pub fn from_config(config: &LidarConfig) -> Self {
+        let network_device = matches!(config.device_type, Some(DeviceConnectionType::Tcp | DeviceConnectionType::Udp));
+        let network_lidar = config.lidar_type == Some(LidarModelType::TofNet);
+
+        Self {
+            intensity: config.intensity.unwrap_or(false),
+            motor_control: config.support_motor_dtr_ctrl.unwrap_or(false),
+            heart_beat: config.support_heart_beat.unwrap_or(false),
+            network: network_device || network_lidar,
+        }
+    }
+
+    pub fn supports(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::Intensity => self.intensity,
+            Capability::MotorControl => self.motor_control,
+            Capability::HeartBeat => self.heart_beat,
+            Capability::Network => self.network,
+        }
+    }
+
+    pub fn require(&self, capability: Capability) -> Result<(), LidarError> {
+        if !self.supports(capability) {
+            return Err(LidarError::with_kind(ErrorKind::Unsupported, &format!("{:?} is not supported by this device", capability)));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn require_fails_for_missing_capability() {
+        let config = LidarConfig {
+            support_motor_dtr_ctrl: Some(true),
+            ..LidarConfig::default()
+        };
+        let capabilities = DeviceCapabilities::from_config(&config);
+
+        assert!(capabilities.require(Capability::MotorControl).is_ok());
+        assert_eq!(ErrorKind::Unsupported, capabilities.require(Capability::Network).unwrap_err().kind);
+    }
+
+    #[test]
+    fn required_by_ignores_disabling_properties() {
+        assert_eq!(Some(Capability::Intensity), Capability::required_by(&LidarProperty::Intensity(true)));
+        assert_eq!(None, Capability::required_by(&LidarProperty::Intensity(false)));
+        assert_eq!(Some(Capability::Network), Capability::required_by(&LidarProperty::DeviceType(DeviceConnectionType::Tcp)));
+        assert_eq!(None, Capability::required_by(&LidarProperty::DeviceType(DeviceConnectionType::Serial)));
+    }
+}
@@ -0,0 +1,227 @@
+use std::path::PathBuf;
+use crate::ydlidar::{DeviceConnectionType, ErrorKind, LidarError, LidarModelType, LidarProperty, ScanFrequency};
+
+/// Lidar settings by name, as returned by `Ydlidar::dump_config` and consumed by `Ydlidar::apply_config`.
+/// A field is `None` when the property was never set, i.e. the SDK default is in effect.
+/// With the `serde` feature this can be loaded straight from a config file; omitted fields stay `None`.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LidarConfig {
+    pub serial_port: Option<PathBuf>,
+    pub ignore_array: Option<String>,
+    pub baud_rate: Option<i32>,
+    pub lidar_type: Option<LidarModelType>,
+    pub device_type: Option<DeviceConnectionType>,
+    pub sample_rate: Option<i32>,
+    pub abnormal_check_count: Option<i32>,
+    pub intensity_bit: Option<i32>,
+    pub max_range: Option<f32>,
+    pub min_range: Option<f32>,
+    pub max_angle: Option<f32>,
+    pub min_angle: Option<f32>,
+    pub scan_frequency: Option<f32>,
+    pub fixed_resolution: Option<bool>,
+    pub reversion: Option<bool>,
+    pub inverted: Option<bool>,
+    pub auto_reconnect: Option<bool>,
+    pub single_channel: Option<bool>,
+    pub intensity: Option<bool>,
+    pub support_motor_dtr_ctrl: Option<bool>,
+    pub support_heart_beat: Option<bool>,
+}
+
+impl LidarConfig {
+    /*
+        Every property that has a value, in the order of the LidarProperty enum.
+        Fails if scan_frequency doesn't make a valid ScanFrequency, since the fields are public.
+    */
+    pub(crate) fn properties(&self) -> Result<Vec<LidarProperty<'_>>, LidarError> {
+        let mut props = Vec::new();
+        if let Some(val) = &self.serial_port { props.push(LidarProperty::SerialPortPath(val)); }
+        if let Some(val) = &self.ignore_array { props.push(LidarProperty::IgnoreArray(val)); }
+        if let Some(val) = self.baud_rate { props.push(LidarProperty::SerialBaudRate(val)); }
+        if let Some(val) = self.lidar_type { props.push(LidarProperty::LidarType(val)); }
+        if let Some(val) = self.device_type { props.push(LidarProperty::DeviceType(val)); }
+        if let Some(val) = self.sample_rate { props.push(LidarProperty::SampleRate(val)); }
+        if let Some(val) = self.abnormal_check_count { props.push(LidarProperty::AbnormalCheckCount(val)); }
+        if let Some(val) = self.intensity_bit { props.push(LidarProperty::IntensityBit(val)); }
+        if let Some(val) = self.max_range { props.push(LidarProperty::MaxRange(val)); }
+        if let Some(val) = self.min_range { props.push(LidarProperty::MinRange(val)); }
+        if let Some(val) = self.max_angle { props.push(LidarProperty::MaxAngle(val)); }
+        if let Some(val) = self.min_angle { props.push(LidarProperty::MinAngle(val)); }
+        if let Some(val) = self.scan_frequency { props.push(LidarProperty::ScanFrequency(ScanFrequency::new(val)?)); }
+        if let Some(val) = self.fixed_resolution { props.push(LidarProperty::FixedResolution(val)); }
+        if let Some(val) = self.reversion { props.push(LidarProperty::Reversion(val)); }
+        if let Some(val) = self.inverted { props.push(LidarProperty::Inverted(val)); }
+        if let Some(val) = self.auto_reconnect { props.push(LidarProperty::AutoReconnect(val)); }
+        if let Some(val) = self.single_channel { props.push(LidarProperty::SingleChannel(val)); }
+        if let Some(val) = self.intensity { props.push(LidarProperty::Intensity(val)); }
+        if let Some(val) = self.support_motor_dtr_ctrl { props.push(LidarProperty::SupportMotorDtrCtrl(val)); }
+        if let Some(val) = self.support_heart_beat { props.push(LidarProperty::SupportHeartBeat(val)); }
+
+        Ok(props)
+    }
+
+    /*
+        Client-side sanity checks for `prop`, including against the limits held by this config: the applied
+        config for a single property, or the incoming config as a whole in apply_config.
+        ScanFrequency needs no check here since the newtype can't hold an invalid value.
+    */
+    pub(crate) fn validate(&self, prop: &LidarProperty) -> Result<(), LidarError> {
+        let invalid = |desc: String| Err(LidarError::with_kind(ErrorKind::InvalidValue, &desc));
+
+        match *prop {
+            LidarProperty::MaxRange(val) | LidarProperty::MinRange(val) if val.is_nan() || val < 0.0 => {
+                invalid(format!("range must be non-negative, got {}", val))
+            }
+            LidarProperty::MaxRange(val) => match self.min_range {
+                Some(min) if val <= min => invalid(format!("MaxRange ({}) must be greater than MinRange ({})", val, min)),
+                _ => Ok(()),
+            },
+            LidarProperty::MinRange(val) => match self.max_range {
+                Some(max) if val >= max => invalid(format!("MinRange ({}) must be less than MaxRange ({})", val, max)),
+                _ => Ok(()),
+            },
+            LidarProperty::MaxAngle(val) | LidarProperty::MinAngle(val) if val.is_nan() => {
+                invalid("angle must be a number, got NaN".to_string())
+            }
+            LidarProperty::MaxAngle(val) => match self.min_angle {
+                Some(min) if val <= min => invalid(format!("MaxAngle ({}) must be greater than MinAngle ({})", val, min)),
+                _ => Ok(()),
+            },
+            LidarProperty::MinAngle(val) => match self.max_angle {
+                Some(max) if val >= max => invalid(format!("MinAngle ({}) must be less than MaxAngle ({})", val, max)),
+                _ => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /*
+        Checks that only make sense once every property is known, i.e. before the device is started.
+        Intensity and IntensityBit have to be set together: either one alone leaves the intensity field
+        of every point zeroed or garbled.
+    */
+    pub(crate) fn check_consistency(&self) -> Result<(), LidarError> {
+        match (self.intensity, self.intensity_bit) {
+            (Some(true), None) => Err(LidarError::with_kind(ErrorKind::InvalidValue, "Intensity is enabled but IntensityBit is not set")),
+            (Some(false) | None, Some(bit)) => Err(LidarError::with_kind(ErrorKind::InvalidValue, &format!("IntensityBit is set to {} but Intensity is not enabled", bit))),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn record(&mut self, prop: &LidarProperty) {
+        match *prop {
+            LidarProperty::SerialPort(str) => self.serial_port = Some(PathBuf::from(str)),
+            LidarProperty::SerialPortPath(path) => self.serial_port = Some(path.to_path_buf()),
+            LidarProperty::IgnoreArray(str) => self.ignore_array = Some(str.to_string()),
+            LidarProperty::NetworkAddress(str) => self.serial_port = Some(PathBuf::from(str)),
+            LidarProperty::NetworkPort(val) => self.baud_rate = Some(val),
+            LidarProperty::SerialBaudRate(val) => self.baud_rate = Some(val),
+            LidarProperty::LidarType(val) => self.lidar_type = Some(val),
+            LidarProperty::DeviceType(val) => self.device_type = Some(val),
+            LidarProperty::SampleRate(val) => self.sample_rate = Some(val),
+            LidarProperty::AbnormalCheckCount(val) => self.abnormal_check_count = Some(val),
+            LidarProperty::IntensityBit(val) => self.intensity_bit = Some(val),
+            LidarProperty::MaxRange(val) => self.max_range = Some(val),
+            LidarProperty::MinRange(val) => self.min_range = Some(val),
+            LidarProperty::MaxAngle(val) => self.max_angle = Some(val),
+            LidarProperty::MinAngle(val) => self.min_angle = Some(val),
+            LidarProperty::ScanFrequency(val) => self.scan_frequency = Some(val.hz()),
+            LidarProperty::FixedResolution(val) => self.fixed_resolution = Some(val),
+            LidarProperty::Reversion(val) => self.reversion = Some(val),
+            LidarProperty::Inverted(val) => self.inverted = Some(val),
+            LidarProperty::AutoReconnect(val) => self.auto_reconnect = Some(val),
+            LidarProperty::SingleChannel(val) => self.single_channel = Some(val),
+            LidarProperty::Intensity(val) => self.intensity = Some(val),
+            LidarProperty::SupportMotorDtrCtrl(val) => self.support_motor_dtr_ctrl = Some(val),
+            LidarProperty::SupportHeartBeat(val) => self.support_heart_beat = Some(val),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_keeps_last_value() {
+        let mut config = LidarConfig::default();
+
+        config.record(&LidarProperty::SerialPort("/dev/ttyUSB0"));
+        config.record(&LidarProperty::ScanFrequency(ScanFrequency::new(10.0).unwrap()));
+        config.record(&LidarProperty::ScanFrequency(ScanFrequency::new(12.0).unwrap()));
+
+        assert_eq!(Some(PathBuf::from("/dev/ttyUSB0")), config.serial_port);
+        assert_eq!(Some(12.0), config.scan_frequency);
+        assert_eq!(None, config.baud_rate);
+    }
+
+    #[test]
+    fn properties_round_trip_through_record() {
+        let config = LidarConfig {
+            serial_port: Some(PathBuf::from("/dev/ttyUSB0")),
+            baud_rate: Some(115200),
+            scan_frequency: Some(8.0),
+            inverted: Some(false),
+            ..LidarConfig::default()
+        };
+
+        let mut recorded = LidarConfig::default();
+        for prop in config.properties().unwrap() {
+            recorded.record(&prop);
+        }
+
+        assert_eq!(config, recorded);
+    }
+
+    #[test]
+    fn validate_rejects_negative_range() {
+        let config = LidarConfig::default();
+
+        assert_eq!(ErrorKind::InvalidValue, config.validate(&LidarProperty::MinRange(-5.0)).unwrap_err().kind);
+        assert!(config.validate(&LidarProperty::MinRange(0.0)).is_ok());
+    }
+
+    #[test]
+    fn validate_checks_against_applied_limits() {
+        let config = LidarConfig {
+            min_range: Some(0.1),
+            max_range: Some(12.0),
+            min_angle: Some(-90.0),
+            max_angle: Some(90.0),
+            ..LidarConfig::default()
+        };
+
+        assert!(config.validate(&LidarProperty::MaxRange(0.1)).is_err());
+        assert!(config.validate(&LidarProperty::MinRange(12.5)).is_err());
+        assert!(config.validate(&LidarProperty::MaxAngle(-90.0)).is_err());
+        assert!(config.validate(&LidarProperty::MinAngle(95.0)).is_err());
+        assert!(config.validate(&LidarProperty::MaxRange(16.0)).is_ok());
+        assert!(config.validate(&LidarProperty::MinAngle(-180.0)).is_ok());
+    }
+
+    #[test]
+    fn properties_rejects_invalid_scan_frequency() {
+        let config = LidarConfig {
+            scan_frequency: Some(-1.0),
+            ..LidarConfig::default()
+        };
+
+        assert!(config.properties().is_err());
+    }
+
+    #[test]
+    fn check_consistency_requires_intensity_and_bit_together() {
+        let enabled_without_bit = LidarConfig { intensity: Some(true), ..LidarConfig::default() };
+        let bit_without_enabled = LidarConfig { intensity_bit: Some(10), ..LidarConfig::default() };
+        let bit_with_disabled = LidarConfig { intensity: Some(false), intensity_bit: Some(10), ..LidarConfig::default() };
+        let paired = LidarConfig { intensity: Some(true), intensity_bit: Some(10), ..LidarConfig::default() };
+
+        assert_eq!(ErrorKind::InvalidValue, enabled_without_bit.check_consistency().unwrap_err().kind);
+        assert_eq!(ErrorKind::InvalidValue, bit_without_enabled.check_consistency().unwrap_err().kind);
+        assert_eq!(ErrorKind::InvalidValue, bit_with_disabled.check_consistency().unwrap_err().kind);
+        assert!(paired.check_consistency().is_ok());
+        assert!(LidarConfig::default().check_consistency().is_ok());
+    }
+}
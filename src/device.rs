@@ -0,0 +1,30 @@
+use crate::ydlidar::{LaserScan, LidarError, ScanIter, Ydlidar};
+
+/// The lifecycle shared by real and simulated lidars, so that scan-consuming code can be
+/// exercised without hardware.
+pub trait LidarDevice {
+    fn initialize(&mut self) -> Result<(), LidarError>;
+    fn turn_on(&mut self) -> Result<(), LidarError>;
+    fn turn_off(&mut self) -> Result<(), LidarError>;
+    fn do_process_simple(&mut self) -> Result<LaserScan, LidarError>;
+    fn is_scanning(&self) -> bool;
+
+    /// See `Ydlidar::shutdown`. Devices without a connection to close only need to be turned off.
+    fn shutdown(&mut self) -> Result<(), LidarError> {
+        if self.is_scanning() { self.turn_off() } else { Ok(()) }
+    }
+
+    /// See `Ydlidar::scans`.
+    fn scans(&mut self) -> ScanIter<'_, Self> where Self: Sized {
+        ScanIter::new(self)
+    }
+}
+
+impl LidarDevice for Ydlidar {
+    fn initialize(&mut self) -> Result<(), LidarError> { Ydlidar::initialize(self) }
+    fn turn_on(&mut self) -> Result<(), LidarError> { Ydlidar::turn_on(self) }
+    fn turn_off(&mut self) -> Result<(), LidarError> { Ydlidar::turn_off(self) }
+    fn do_process_simple(&mut self) -> Result<LaserScan, LidarError> { Ydlidar::do_process_simple(self) }
+    fn is_scanning(&self) -> bool { Ydlidar::is_scanning(self) }
+    fn shutdown(&mut self) -> Result<(), LidarError> { Ydlidar::shutdown(self) }
+}
@@ -0,0 +1,75 @@
+/*
+    Conversions into the point types of common geometry crates, each behind its own feature so the
+    core crate doesn't pull them in. Like LaserScan::to_point_cloud, invalid (zero range) points are skipped.
+*/
+use crate::ydlidar::{LaserPoint, LaserScan};
+
+fn valid_xy(scan: &LaserScan) -> impl Iterator<Item=(f32, f32)> + '_ {
+    scan.points().iter()
+        .filter(|p| p.range() > 0.0)
+        .map(LaserPoint::xy)
+}
+
+/// Cartesian points in meters, e.g. for building a KD-tree:
+///
+/// ```ignore
+/// use kiddo::{KdTree, SquaredEuclidean};
+/// use nalgebra::Point2;
+///
+/// let points: Vec<Point2<f32>> = (&scan).into();
+/// let mut tree: KdTree<f32, 2> = KdTree::new();
+/// for (i, p) in points.iter().enumerate() {
+///     tree.add(&[p.x, p.y], i as u64);
+/// }
+/// let nearest = tree.nearest_one::<SquaredEuclidean>(&[0.5, 0.0]);
+/// ```
+#[cfg(feature = "nalgebra")]
+impl From<&LaserScan> for Vec<nalgebra::Point2<f32>> {
+    fn from(scan: &LaserScan) -> Self {
+        valid_xy(scan).map(|(x, y)| nalgebra::Point2::new(x, y)).collect()
+    }
+}
+
+/// Cartesian points in meters, e.g. for building an R-tree:
+///
+/// ```ignore
+/// use geo::Point;
+/// use rstar::RTree;
+///
+/// let points: Vec<Point<f32>> = (&scan).into();
+/// let tree = RTree::bulk_load(points);
+/// let nearest = tree.nearest_neighbor(&Point::new(0.5, 0.0));
+/// ```
+#[cfg(feature = "geo")]
+impl From<&LaserScan> for Vec<geo::Point<f32>> {
+    fn from(scan: &LaserScan) -> Self {
+        valid_xy(scan).map(|(x, y)| geo::Point::new(x, y)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan() -> LaserScan {
+        LaserScan::new(0, vec![LaserPoint::new(0.0, 2.0, 0.0), LaserPoint::new(1.0, 0.0, 0.0)])
+    }
+
+    #[cfg(feature = "nalgebra")]
+    #[test]
+    fn nalgebra_points_skip_invalid_returns() {
+        let points: Vec<nalgebra::Point2<f32>> = (&scan()).into();
+
+        assert_eq!(1, points.len());
+        assert_eq!((2.0, 0.0), (points[0].x, points[0].y));
+    }
+
+    #[cfg(feature = "geo")]
+    #[test]
+    fn geo_points_skip_invalid_returns() {
+        let points: Vec<geo::Point<f32>> = (&scan()).into();
+
+        assert_eq!(1, points.len());
+        assert_eq!((2.0, 0.0), (points[0].x(), points[0].y()));
+    }
+}
@@ -1 +1,42 @@
-pub mod ydlidar;
\ No newline at end of file
+/*
+    Forward to the log crate when the 'log' feature is enabled and expand to nothing otherwise,
+    so call sites don't need to repeat the feature gate.
+*/
+macro_rules! log_debug {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "log")]
+        log::debug!($($arg)*);
+    };
+}
+
+macro_rules! log_info {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "log")]
+        log::info!($($arg)*);
+    };
+}
+
+macro_rules! log_warn {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "log")]
+        log::warn!($($arg)*);
+    };
+}
+
+pub mod builder;
+pub mod capabilities;
+pub mod config;
+pub mod device;
+#[cfg(any(feature = "nalgebra", feature = "geo"))]
+mod geometry;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+pub mod recorder;
+pub mod stream;
+pub mod ydlidar;
+
+pub use config::LidarConfig;
+pub use device::LidarDevice;
+pub use recorder::{ScanPlayer, ScanRecorder};
+pub use ydlidar::{DeviceConnectionType, DeviceInfo, ErrorKind, FfiLaserPoint, LaserPoint, LaserScan, LidarError, LidarModelType, LidarProperty, PortInfo, ScanFrequency, ScanStats, Summary, Ydlidar};
+pub use ydlidar::{ignore_ranges, list_ports, sdk_version};
@@ -0,0 +1,96 @@
+use std::collections::VecDeque;
+use crate::device::LidarDevice;
+use crate::ydlidar::{ErrorKind, LaserScan, LidarError};
+
+/// A `LidarDevice` that replays a fixed sequence of scans, then turns itself off.
+pub struct MockLidar {
+    scans: VecDeque<LaserScan>,
+    initialized: bool,
+    scanning: bool,
+}
+
+impl MockLidar {
+    pub fn new(scans: impl IntoIterator<Item=LaserScan>) -> Self {
+        Self {
+            scans: scans.into_iter().collect(),
+            initialized: false,
+            scanning: false,
+        }
+    }
+}
+
+impl LidarDevice for MockLidar {
+    fn initialize(&mut self) -> Result<(), LidarError> {
+        self.initialized = true;
+        Ok(())
+    }
+
+    fn turn_on(&mut self) -> Result<(), LidarError> {
+        if !self.initialized {
+            return Err(LidarError::with_kind(ErrorKind::TurnOnFailed, "mock lidar is not initialized"));
+        }
+
+        self.scanning = true;
+        Ok(())
+    }
+
+    fn turn_off(&mut self) -> Result<(), LidarError> {
+        self.scanning = false;
+        Ok(())
+    }
+
+    fn do_process_simple(&mut self) -> Result<LaserScan, LidarError> {
+        if !self.scanning {
+            return Err(LidarError::with_kind(ErrorKind::NotScanning, "mock lidar is not scanning"));
+        }
+
+        let scan = self.scans.pop_front()
+            .ok_or_else(|| LidarError::with_kind(ErrorKind::ProcessFailed, "mock lidar has no scans left"))?;
+        if self.scans.is_empty() {
+            self.scanning = false;
+        }
+
+        Ok(scan)
+    }
+
+    fn is_scanning(&self) -> bool { self.scanning }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ydlidar::LaserPoint;
+
+    fn scan(stamp: u64) -> LaserScan {
+        LaserScan::new(stamp, vec![LaserPoint::new(0.0, 1.0, 0.0)])
+    }
+
+    #[test]
+    fn scans_replays_every_canned_scan() {
+        let mut lidar = MockLidar::new(vec![scan(1), scan(2), scan(3)]);
+        lidar.initialize().unwrap();
+        lidar.turn_on().unwrap();
+
+        let stamps: Vec<u64> = lidar.scans().map(|s| s.unwrap().stamp()).collect();
+
+        assert_eq!(vec![1, 2, 3], stamps);
+        assert!(!lidar.is_scanning());
+    }
+
+    #[test]
+    fn scans_can_be_limited() {
+        let mut lidar = MockLidar::new(vec![scan(1), scan(2), scan(3)]);
+        lidar.initialize().unwrap();
+        lidar.turn_on().unwrap();
+
+        assert_eq!(2, lidar.scans().take(2).count());
+        assert!(lidar.is_scanning());
+    }
+
+    #[test]
+    fn turn_on_requires_initialize() {
+        let mut lidar = MockLidar::new(Vec::new());
+
+        assert_eq!(ErrorKind::TurnOnFailed, lidar.turn_on().unwrap_err().kind);
+    }
+}
@@ -0,0 +1,266 @@
+/*
+    Recording format, all integers and floats little-endian:
+
+        header:  RECORDING_MAGIC (8 bytes), RECORDING_VERSION (u16)
+        scan:    stamp (u64), point count (u32), then per point angle, range, intensity (3 x f32)
+
+    Scans follow the header back to back until the end of the file.
+*/
+use std::io::{self, Read, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::device::LidarDevice;
+use crate::ydlidar::{ErrorKind, LaserPoint, LaserScan, LidarError, DEFAULT_MAX_POINTS};
+
+pub const RECORDING_MAGIC: &[u8; 8] = b"YDLSCANS";
+pub const RECORDING_VERSION: u16 = 1;
+
+/// Writes scans to `writer` in the recording format read by `ScanPlayer`.
+pub struct ScanRecorder<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> ScanRecorder<W> {
+    /// Writes the header immediately.
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writer.write_all(RECORDING_MAGIC)?;
+        writer.write_all(&RECORDING_VERSION.to_le_bytes())?;
+
+        Ok(Self { writer })
+    }
+
+    pub fn record(&mut self, scan: &LaserScan) -> io::Result<()> {
+        let count = u32::try_from(scan.points().len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "scan has too many points to record"))?;
+
+        self.writer.write_all(&scan.stamp().to_le_bytes())?;
+        self.writer.write_all(&count.to_le_bytes())?;
+        for point in scan.points() {
+            self.writer.write_all(&point.angle().to_le_bytes())?;
+            self.writer.write_all(&point.range().to_le_bytes())?;
+            self.writer.write_all(&point.intensity().to_le_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    /// Flushes and returns the underlying writer.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Reads back a recording made by `ScanRecorder`, at the pace it was recorded (see `realtime`). Iteration ends at the end of the recording or at the first
+/// malformed scan. Also implements `LidarDevice`, so a recording can stand in for a lidar.
+pub struct ScanPlayer<R: Read> {
+    reader: R,
+    next: Option<LaserScan>,
+    realtime: bool,
+    clock: Option<(u64, Instant)>,
+    scanning: bool,
+}
+
+impl<R: Read> ScanPlayer<R> {
+    /// Fails with `InvalidData` if `reader` doesn't start with a recording header of a supported version.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != RECORDING_MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a scan recording"));
+        }
+
+        let version = u16::from_le_bytes(read_array(&mut reader)?);
+        if version != RECORDING_VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unsupported recording version {}", version)));
+        }
+
+        let next = read_scan(&mut reader);
+        Ok(Self {
+            reader,
+            next,
+            realtime: true,
+            clock: None,
+            scanning: false,
+        })
+    }
+
+    /// Enabled by default: each scan is held back until as much time has passed since the first one as
+    /// separates their stamps, reproducing the pace of the original session. Disable it to replay as fast
+    /// as the recording can be read.
+    pub fn realtime(mut self, enabled: bool) -> Self {
+        self.realtime = enabled;
+        self
+    }
+
+    fn wait_for(&mut self, stamp: u64) {
+        let (first_stamp, started) = *self.clock.get_or_insert((stamp, Instant::now()));
+        let due = started + Duration::from_nanos(stamp.saturating_sub(first_stamp));
+        let now = Instant::now();
+        if due > now {
+            thread::sleep(due - now);
+        }
+    }
+}
+
+impl<R: Read> Iterator for ScanPlayer<R> {
+    type Item = LaserScan;
+
+    fn next(&mut self) -> Option<LaserScan> {
+        let scan = self.next.take()?;
+        self.next = read_scan(&mut self.reader);
+
+        if self.realtime {
+            self.wait_for(scan.stamp());
+        }
+
+        Some(scan)
+    }
+}
+
+impl<R: Read> LidarDevice for ScanPlayer<R> {
+    fn initialize(&mut self) -> Result<(), LidarError> { Ok(()) }
+
+    fn turn_on(&mut self) -> Result<(), LidarError> {
+        /*
+            An exhausted recording never starts scanning, so ScanIter over it ends right away.
+        */
+        self.scanning = self.next.is_some();
+        Ok(())
+    }
+
+    fn turn_off(&mut self) -> Result<(), LidarError> {
+        self.scanning = false;
+        Ok(())
+    }
+
+    fn do_process_simple(&mut self) -> Result<LaserScan, LidarError> {
+        if !self.scanning {
+            return Err(LidarError::with_kind(ErrorKind::NotScanning, "scan player is not scanning, call turn_on first"));
+        }
+
+        let scan = self.next()
+            .ok_or_else(|| LidarError::with_kind(ErrorKind::ProcessFailed, "recording has no scans left"))?;
+        if self.next.is_none() {
+            self.scanning = false;
+        }
+
+        Ok(scan)
+    }
+
+    fn is_scanning(&self) -> bool { self.scanning }
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_f32(reader: &mut impl Read) -> io::Result<f32> {
+    Ok(f32::from_le_bytes(read_array(reader)?))
+}
+
+/*
+    Any read error, including a truncated scan, ends the recording.
+    Scans of any size that record accepts are read back, but the count is untrusted, so at most
+    DEFAULT_MAX_POINTS are allocated up front; a corrupt count runs into the end of the data instead.
+*/
+fn read_scan(reader: &mut impl Read) -> Option<LaserScan> {
+    let stamp = u64::from_le_bytes(read_array(reader).ok()?);
+    let count = usize::try_from(u32::from_le_bytes(read_array(reader).ok()?)).ok()?;
+
+    let mut points = Vec::with_capacity(count.min(DEFAULT_MAX_POINTS));
+    for _ in 0..count {
+        let angle = read_f32(reader).ok()?;
+        let range = read_f32(reader).ok()?;
+        let intensity = read_f32(reader).ok()?;
+        points.push(LaserPoint::new(angle, range, intensity));
+    }
+
+    Some(LaserScan::new(stamp, points))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(stamp: u64) -> LaserScan {
+        LaserScan::new(stamp, vec![LaserPoint::new(0.5, 1.25, 100.0), LaserPoint::new(-0.5, 0.0, 0.0)])
+    }
+
+    fn recording(scans: &[LaserScan]) -> Vec<u8> {
+        let mut recorder = ScanRecorder::new(Vec::new()).unwrap();
+        for scan in scans {
+            recorder.record(scan).unwrap();
+        }
+        recorder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn player_replays_recorded_scans() {
+        let bytes = recording(&[scan(1), scan(2)]);
+
+        let scans: Vec<LaserScan> = ScanPlayer::new(bytes.as_slice()).unwrap().realtime(false).collect();
+
+        assert_eq!(2, scans.len());
+        assert_eq!(1, scans[0].stamp());
+        assert_eq!(scan(2).points(), scans[1].points());
+    }
+
+    #[test]
+    fn player_stops_at_truncated_scan() {
+        let mut bytes = recording(&[scan(1), scan(2)]);
+        bytes.truncate(bytes.len() - 1);
+
+        assert_eq!(1, ScanPlayer::new(bytes.as_slice()).unwrap().count());
+    }
+
+    #[test]
+    fn player_replays_scans_above_default_max_points() {
+        let points = vec![LaserPoint::new(0.0, 1.0, 0.0); DEFAULT_MAX_POINTS + 1];
+        let bytes = recording(&[LaserScan::new(1, points), scan(2)]);
+
+        let scans: Vec<LaserScan> = ScanPlayer::new(bytes.as_slice()).unwrap().collect();
+
+        assert_eq!(2, scans.len());
+        assert_eq!(DEFAULT_MAX_POINTS + 1, scans[0].points().len());
+    }
+
+    #[test]
+    fn player_stops_at_corrupt_point_count() {
+        let mut bytes = recording(&[scan(1)]);
+        let count_offset = RECORDING_MAGIC.len() + 2 + 8;
+        bytes[count_offset..count_offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        assert_eq!(0, ScanPlayer::new(bytes.as_slice()).unwrap().count());
+    }
+
+    #[test]
+    fn player_rejects_foreign_data() {
+        let err = ScanPlayer::new(b"not a recording".as_slice()).err().unwrap();
+
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+    }
+
+    #[test]
+    fn player_keeps_scan_spacing_by_default() {
+        let bytes = recording(&[scan(0), scan(20_000_000)]);
+        let started = Instant::now();
+
+        assert_eq!(2, ScanPlayer::new(bytes.as_slice()).unwrap().count());
+        assert!(started.elapsed() >= Duration::from_millis(20));
+    }
+
+    #[test]
+    fn player_stands_in_for_a_lidar() {
+        let bytes = recording(&[scan(1), scan(2), scan(3)]);
+        let mut player = ScanPlayer::new(bytes.as_slice()).unwrap().realtime(false);
+        player.initialize().unwrap();
+        player.turn_on().unwrap();
+
+        let stamps: Vec<u64> = player.scans().map(|s| s.unwrap().stamp()).collect();
+
+        assert_eq!(vec![1, 2, 3], stamps);
+    }
+}
@@ -0,0 +1,161 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+#[cfg(feature = "tokio")]
+use crate::device::LidarDevice;
+use crate::ydlidar::{ErrorKind, LaserScan, LidarError, Ydlidar};
+
+/// Handle to a background thread that owns a `Ydlidar` and forwards its scans.
+pub struct StreamHandle {
+    receiver: Receiver<LaserScan>,
+    stop: Arc<AtomicBool>,
+    worker: JoinHandle<Result<(), LidarError>>,
+}
+
+impl StreamHandle {
+    pub fn receiver(&self) -> &Receiver<LaserScan> { &self.receiver }
+
+    /// Signals the worker to stop, waits for it to turn the lidar off and disconnect, and returns
+    /// the error that ended the stream, if any.
+    pub fn stop(self) -> Result<(), LidarError> {
+        self.stop.store(true, Ordering::Relaxed);
+        drop(self.receiver);
+
+        self.worker.join().unwrap_or_else(|_| Err(LidarError::with_kind(ErrorKind::ProcessFailed, "stream worker panicked")))
+    }
+}
+
+/*
+    The handle is created by `setup` on the worker thread itself, so that configuration errors are
+    reported through StreamHandle::stop like any other. `setup` should only configure properties; the
+    worker takes care of initialize/turn_on and of turn_off/disconnect once the stream ends.
+*/
+pub fn spawn_stream<F>(setup: F) -> StreamHandle
+    where F: FnOnce() -> Result<Ydlidar, LidarError> + Send + 'static
+{
+    let (sender, receiver) = channel();
+    let stop = Arc::new(AtomicBool::new(false));
+    let worker_stop = stop.clone();
+
+    let worker = thread::spawn(move || {
+        let mut lidar = setup()?;
+        lidar.connect()?;
+
+        let mut result = Ok(());
+        while !worker_stop.load(Ordering::Relaxed) {
+            match lidar.do_process_simple() {
+                Ok(scan) => {
+                    if sender.send(scan).is_err() {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+
+        result.and(lidar.shutdown())
+    });
+
+    StreamHandle {
+        receiver,
+        stop,
+        worker,
+    }
+}
+
+#[cfg(feature = "tokio")]
+pub const SCAN_STREAM_CAPACITY: usize = 4;
+
+#[cfg(feature = "tokio")]
+impl Ydlidar {
+    /// Moves the lidar onto a dedicated thread and yields its scans asynchronously. The lidar should already be
+    /// turned on; the stream ends under the same conditions as `Ydlidar::scans`. Dropping the stream makes the
+    /// worker turn the lidar off and disconnect.
+    pub fn scan_stream(self) -> impl tokio_stream::Stream<Item = Result<LaserScan, LidarError>> {
+        device_scan_stream(self)
+    }
+}
+
+/*
+    The worker behind Ydlidar::scan_stream, generic so that it can be tested with a MockLidar.
+*/
+#[cfg(feature = "tokio")]
+fn device_scan_stream<D>(mut device: D) -> tokio_stream::wrappers::ReceiverStream<Result<LaserScan, LidarError>>
+    where D: LidarDevice + Send + 'static
+{
+    let (sender, receiver) = tokio::sync::mpsc::channel(SCAN_STREAM_CAPACITY);
+
+    thread::spawn(move || {
+        for result in device.scans() {
+            if sender.blocking_send(result).is_err() {
+                break;
+            }
+        }
+
+        let _ = device.shutdown();
+    });
+
+    tokio_stream::wrappers::ReceiverStream::new(receiver)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stop_returns_setup_error() {
+        let handle = spawn_stream(|| Err(LidarError::new("no device")));
+
+        assert_eq!("no device", handle.stop().unwrap_err().description);
+    }
+
+    /*
+        Counts shutdowns of the wrapped mock, since the device itself moves onto the worker thread.
+    */
+    #[cfg(feature = "tokio")]
+    struct TrackedLidar {
+        inner: crate::mock::MockLidar,
+        shutdowns: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[cfg(feature = "tokio")]
+    impl LidarDevice for TrackedLidar {
+        fn initialize(&mut self) -> Result<(), LidarError> { self.inner.initialize() }
+        fn turn_on(&mut self) -> Result<(), LidarError> { self.inner.turn_on() }
+        fn turn_off(&mut self) -> Result<(), LidarError> { self.inner.turn_off() }
+        fn do_process_simple(&mut self) -> Result<LaserScan, LidarError> { self.inner.do_process_simple() }
+        fn is_scanning(&self) -> bool { self.inner.is_scanning() }
+
+        fn shutdown(&mut self) -> Result<(), LidarError> {
+            self.shutdowns.fetch_add(1, Ordering::SeqCst);
+            self.inner.shutdown()
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn dropping_scan_stream_shuts_the_device_down() {
+        use crate::ydlidar::LaserPoint;
+
+        let scans = (0..100).map(|stamp| LaserScan::new(stamp, vec![LaserPoint::new(0.0, 1.0, 0.0)]));
+        let mut inner = crate::mock::MockLidar::new(scans);
+        inner.initialize().unwrap();
+        inner.turn_on().unwrap();
+        let shutdowns = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+        let stream = device_scan_stream(TrackedLidar { inner, shutdowns: shutdowns.clone() });
+        drop(stream);
+
+        for _ in 0..100 {
+            if shutdowns.load(Ordering::SeqCst) > 0 {
+                break;
+            }
+            thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(1, shutdowns.load(Ordering::SeqCst));
+    }
+}
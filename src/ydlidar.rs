@@ -1,19 +1,66 @@
-use std::ffi::{c_void, CStr, CString};
+use std::f32::consts::{PI, TAU};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::fmt;
+use std::io::{self, Write};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use ydlidar_sdk_sys::*;
+pub use ydlidar_sdk_sys::LaserPoint as FfiLaserPoint;
+use crate::capabilities::{Capability, DeviceCapabilities};
+use crate::config::LidarConfig;
+use crate::device::LidarDevice;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    InitFailed,
+    TurnOnFailed,
+    TurnOffFailed,
+    PropertyRejected,
+    ProcessFailed,
+    NotScanning,
+    QueryFailed,
+    Unsupported,
+    InvalidValue,
+}
 
 #[derive(Debug)]
 pub struct LidarError {
+    pub kind: ErrorKind,
     pub description: String,
 }
 
 impl LidarError {
     pub(crate) fn new(desc: &str) -> Self {
+        Self::with_kind(ErrorKind::PropertyRejected, desc)
+    }
+
+    fn for_property(self, name: &str) -> Self {
+        Self {
+            kind: self.kind,
+            description: format!("failed to set {}: {}", name, self.description),
+        }
+    }
+
+    pub(crate) fn with_kind(kind: ErrorKind, desc: &str) -> Self {
+        log_warn!("{:?}: {}", kind, desc);
         Self {
+            kind,
             description: desc.to_string()
         }
     }
 }
 
+impl fmt::Display for LidarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description)
+    }
+}
+
+impl Error for LidarError {}
+
 pub struct Ydlidar {
     lidar: *mut YDLidar,
     /*
@@ -23,28 +70,87 @@ pub struct Ydlidar {
     */
     lidar_port: CString,
     ignore_array: CString,
+    config: LidarConfig,
+    phase_lock: bool,
+    skip_invalid: bool,
+    scanning: bool,
+    last_scan_time: Option<f32>,
+    max_points: usize,
+    capabilities: DeviceCapabilities,
 }
 
+/*
+    SAFETY: the SDK handle is created by lidarCreate and owned exclusively by this struct, and the
+    pinned strings it points into move along with it. Handing the whole thing to another thread is
+    fine as long as only one thread uses it at a time, which &mut self on every SDK call enforces.
+    Ydlidar is deliberately not Sync: the SDK does no locking of its own.
+*/
+unsafe impl Send for Ydlidar {}
+
 impl Ydlidar {
     pub fn new() -> Self {
+        unsafe { Self::from_raw(lidarCreate()) }
+    }
+
+    /// Takes ownership of an existing SDK handle; it is destroyed with `lidarDestroy` when the `Ydlidar` is dropped.
+    /// The handle is assumed to be turned off; use `from_raw_scanning` for one that was already turned on.
+    ///
+    /// # Safety
+    /// `ptr` must have been returned by `lidarCreate`, must not have been destroyed, and must not be used
+    /// by anything other than the returned `Ydlidar` afterwards.
+    pub unsafe fn from_raw(ptr: *mut YDLidar) -> Self {
+        Self::from_raw_parts(ptr, false)
+    }
+
+    /// Like `from_raw`, for a handle that was already turned on: scans can be read from it straight away,
+    /// and it is turned off when dropped.
+    ///
+    /// # Safety
+    /// Same as `from_raw`.
+    pub unsafe fn from_raw_scanning(ptr: *mut YDLidar) -> Self {
+        Self::from_raw_parts(ptr, true)
+    }
+
+    unsafe fn from_raw_parts(ptr: *mut YDLidar, scanning: bool) -> Self {
         Self {
-            lidar: unsafe { lidarCreate() },
+            lidar: ptr,
             lidar_port: CString::default(),
             ignore_array: CString::default(),
+            config: LidarConfig::default(),
+            phase_lock: false,
+            skip_invalid: false,
+            scanning,
+            last_scan_time: None,
+            max_points: DEFAULT_MAX_POINTS,
+            capabilities: DeviceCapabilities::all(),
         }
     }
 
     /*
-        TODO: Implement some kind of builder pattern with reasonable defaults.
-        Having to call set_property and handling the Result<> each time is ugly.
+        For configuring many properties at once, see LidarBuilder.
     */
+    /// Errors name the rejected property, e.g. "failed to set ScanFrequency: <description>".
     pub fn set_property(&mut self, prop: LidarProperty) -> Result<(), LidarError> {
+        let name = prop.name();
+        self.check_capability(&prop)
+            .and_then(|()| self.config.validate(&prop))
+            .and_then(|()| self.apply_property(prop))
+            .map_err(|e| e.for_property(name))
+    }
+
+    /*
+        Sends 'prop' to the SDK and records it, without validating it against the applied config.
+    */
+    fn apply_property(&mut self, prop: LidarProperty) -> Result<(), LidarError> {
         let ok = match prop {
-            LidarProperty::SerialPort(str) => self.set_string_property(LidarProperty_LidarPropSerialPort, str),
-            LidarProperty::IgnoreArray(str) => self.set_string_property(LidarProperty_LidarPropIgnoreArray, str),
+            LidarProperty::SerialPort(str) => self.set_string_property(LidarProperty_LidarPropSerialPort, str)?,
+            LidarProperty::SerialPortPath(path) => self.set_cstring_property(LidarProperty_LidarPropSerialPort, path_to_cstring(path)?),
+            LidarProperty::IgnoreArray(str) => self.set_string_property(LidarProperty_LidarPropIgnoreArray, str)?,
+            LidarProperty::NetworkAddress(str) => self.set_string_property(LidarProperty_LidarPropSerialPort, str)?,
+            LidarProperty::NetworkPort(val) => self.set_int_property(LidarProperty_LidarPropSerialBaudrate, val),
             LidarProperty::SerialBaudRate(val) => self.set_int_property(LidarProperty_LidarPropSerialBaudrate, val),
-            LidarProperty::LidarType(val) => self.set_int_property(LidarProperty_LidarPropLidarType, val),
-            LidarProperty::DeviceType(val) => self.set_int_property(LidarProperty_LidarPropDeviceType, val),
+            LidarProperty::LidarType(val) => self.set_int_property(LidarProperty_LidarPropLidarType, val.as_raw()),
+            LidarProperty::DeviceType(val) => self.set_int_property(LidarProperty_LidarPropDeviceType, val.as_raw()),
             LidarProperty::SampleRate(val) => self.set_int_property(LidarProperty_LidarPropSampleRate, val),
             LidarProperty::AbnormalCheckCount(val) => self.set_int_property(LidarProperty_LidarPropAbnormalCheckCount, val),
             LidarProperty::IntensityBit(val) => self.set_int_property(LidarProperty_LidarPropIntenstiyBit, val),
@@ -52,7 +158,7 @@ impl Ydlidar {
             LidarProperty::MinRange(val) => self.set_float_property(LidarProperty_LidarPropMinRange, val),
             LidarProperty::MaxAngle(val) => self.set_float_property(LidarProperty_LidarPropMaxAngle, val),
             LidarProperty::MinAngle(val) => self.set_float_property(LidarProperty_LidarPropMinAngle, val),
-            LidarProperty::ScanFrequency(val) => self.set_float_property(LidarProperty_LidarPropScanFrequency, val),
+            LidarProperty::ScanFrequency(val) => self.set_float_property(LidarProperty_LidarPropScanFrequency, val.hz()),
             LidarProperty::FixedResolution(val) => self.set_bool_property(LidarProperty_LidarPropFixedResolution, val),
             LidarProperty::Reversion(val) => self.set_bool_property(LidarProperty_LidarPropReversion, val),
             LidarProperty::Inverted(val) => self.set_bool_property(LidarProperty_LidarPropInverted, val),
@@ -64,15 +170,100 @@ impl Ydlidar {
         };
 
         if !ok {
-            unsafe {
-                let error_description = CStr::from_ptr(DescribeError(self.lidar));
-                return Err(LidarError::new(error_description.to_str().unwrap()));
-            };
+            return Err(LidarError::new(&self.last_error_description()));
+        }
+
+        self.config.record(&prop);
+        Ok(())
+    }
+
+    /// Upper bound on the number of points accepted from a single scan (`DEFAULT_MAX_POINTS` by default).
+    /// Scans reporting more are rejected as corrupt instead of being allocated.
+    pub fn set_max_points(&mut self, max_points: usize) {
+        self.max_points = max_points;
+    }
+
+    /// When enabled, points with a range of 0 (no return) are left out of processed scans as they are read
+    /// from the SDK. The stamp and the order of the remaining points are unchanged. Off by default.
+    pub fn set_skip_invalid(&mut self, enabled: bool) {
+        self.skip_invalid = enabled;
+    }
+
+    /// Relinquishes ownership of the SDK handle without destroying it. The caller becomes responsible
+    /// for eventually passing it to `lidarDestroy`. Check `is_scanning` beforehand to choose between `from_raw` and `from_raw_scanning`.
+    pub fn into_raw(mut self) -> *mut YDLidar {
+        /*
+            The SDK may still be pointing into 'lidar_port' and 'ignore_array', so they are leaked
+            along with the handle. Nulling 'lidar' keeps Drop from destroying it.
+        */
+        std::mem::forget(std::mem::take(&mut self.lidar_port));
+        std::mem::forget(std::mem::take(&mut self.ignore_array));
+        std::mem::replace(&mut self.lidar, std::ptr::null_mut())
+    }
+
+    /// Applies every property set in `config`, in declaration order, stopping at the first error.
+    /// Properties left as `None` are not touched. The values are validated against each other before any
+    /// is applied, rather than against the current config, so the range and angle limits can be moved past
+    /// their old values in one go.
+    pub fn apply_config(&mut self, config: &LidarConfig) -> Result<(), LidarError> {
+        let props = config.properties()?;
+        for prop in &props {
+            self.check_capability(prop)
+                .and_then(|()| config.validate(prop))
+                .map_err(|e| e.for_property(prop.name()))?;
+        }
+
+        for prop in props {
+            let name = prop.name();
+            self.apply_property(prop).map_err(|e| e.for_property(name))?;
         }
 
         Ok(())
     }
 
+    /// Returns a snapshot of every property successfully applied through `set_property`.
+    pub fn dump_config(&self) -> LidarConfig {
+        self.config.clone()
+    }
+
+    /// The features properties are checked against. The SDK offers no way to probe them from the unit, so a
+    /// new handle assumes every feature is present (`DeviceCapabilities::all`) until told otherwise with
+    /// `set_capabilities`, as `LidarBuilder::for_model` does. Gating is therefore opt-in.
+    pub fn capabilities(&self) -> DeviceCapabilities {
+        self.capabilities
+    }
+
+    /// Declares which features the unit has. From then on, properties that need a missing feature
+    /// (see `Capability::required_by`) fail with `ErrorKind::Unsupported` without reaching the SDK.
+    pub fn set_capabilities(&mut self, capabilities: DeviceCapabilities) {
+        self.capabilities = capabilities;
+    }
+
+    fn check_capability(&self, prop: &LidarProperty) -> Result<(), LidarError> {
+        match Capability::required_by(prop) {
+            Some(capability) => self.capabilities.require(capability),
+            None => Ok(()),
+        }
+    }
+
+    /// When enabled, every processed scan is reordered so that it starts at `PHASE_LOCK_REFERENCE_ANGLE`
+    /// and continues counter-clockwise. This fixes where each scan starts, but point indices only stay stable
+    /// from one scan to the next if the point count per revolution is constant too, e.g. with `FixedResolution(true)`
+    /// and without `set_skip_invalid`. For indexing that is fixed regardless, see `LaserScan::ranges_fixed`.
+    /// This costs a sort per scan and only makes sense when each scan covers a full revolution.
+    pub fn set_phase_lock(&mut self, enabled: bool) {
+        self.phase_lock = enabled;
+    }
+
+    fn last_error_description(&self) -> String {
+        let error_description = unsafe { CStr::from_ptr(DescribeError(self.lidar)) };
+        describe_error(error_description)
+    }
+
+    fn sdk_error(&self, kind: ErrorKind) -> LidarError {
+        LidarError::with_kind(kind, &self.last_error_description())
+    }
+
     fn set_bool_property(&mut self, property_index: u32, value: bool) -> bool {
         unsafe {
             let val_ptr: *const bool = &value;
@@ -94,14 +285,21 @@ impl Ydlidar {
         }
     }
 
-    fn set_string_property(&mut self, property_index: u32, value: &str) -> bool {
+    fn set_string_property(&mut self, property_index: u32, value: &str) -> Result<bool, LidarError> {
+        let value = CString::new(value)
+            .map_err(|_| LidarError::with_kind(ErrorKind::InvalidValue, &format!("string property contains a NUL byte: {:?}", value)))?;
+
+        Ok(self.set_cstring_property(property_index, value))
+    }
+
+    fn set_cstring_property(&mut self, property_index: u32, value: CString) -> bool {
         let (string, string_len) = match property_index {
             LidarProperty_LidarPropSerialPort => {
-                self.lidar_port = CString::new(value).unwrap();
+                self.lidar_port = value;
                 (self.lidar_port.as_ptr() as *const c_void, self.lidar_port.as_bytes().len().try_into().unwrap())
             }
             LidarProperty_LidarPropIgnoreArray => {
-                self.ignore_array = CString::new(value).unwrap();
+                self.ignore_array = value;
                 (self.ignore_array.as_ptr() as *const c_void, self.ignore_array.as_bytes().len().try_into().unwrap())
             }
             _ => panic!("Unknown string property {}", property_index),
@@ -111,77 +309,542 @@ impl Ydlidar {
         }
     }
 
+    /// Fails with `InvalidValue` without touching the device if the applied properties are inconsistent,
+    /// e.g. `Intensity` enabled without an `IntensityBit`.
     pub fn initialize(&mut self) -> Result<(), LidarError> {
+        self.config.check_consistency()?;
+        log_debug!("initializing lidar on {:?}", self.config.serial_port);
         unsafe {
             if !initialize(self.lidar) {
-                let error_description = CStr::from_ptr(DescribeError(self.lidar));
-                return Err(LidarError::new(error_description.to_str().unwrap()));
+                return Err(self.sdk_error(ErrorKind::InitFailed));
             }
         }
 
+        log_info!("lidar initialized");
         Ok(())
     }
 
+    /// Calls `initialize` up to `attempts` times, disconnecting after every failure and waiting `delay` before the
+    /// next try. Useful right after the device has been plugged in, when the first attempt often fails.
+    /// Returns the last error if every attempt fails.
+    pub fn initialize_retry(&mut self, attempts: usize, delay: Duration) -> Result<(), LidarError> {
+        let mut last_error = None;
+
+        for attempt in 0..attempts {
+            if attempt > 0 {
+                thread::sleep(delay);
+            }
+
+            match self.initialize() {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    self.disconnect();
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| LidarError::with_kind(ErrorKind::InitFailed, "no initialize attempts were made")))
+    }
+
     pub fn disconnect(&mut self) {
         unsafe {
             disconnecting(self.lidar);
         }
+        self.scanning = false;
+        log_debug!("lidar disconnected");
+    }
+
+    /// Runs `initialize` then `turn_on`. If the lidar fails to turn on it is disconnected again before the error is returned.
+    pub fn connect(&mut self) -> Result<(), LidarError> {
+        self.initialize()?;
+        if let Err(e) = self.turn_on() {
+            self.disconnect();
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    /// Runs `turn_off` (skipped if the lidar isn't scanning) then `disconnect`. The lidar is disconnected
+    /// even if turning it off fails, in which case that error is returned. Dropping a lidar that is still
+    /// scanning does the same, minus the error.
+    pub fn shutdown(&mut self) -> Result<(), LidarError> {
+        let turned_off = if self.scanning { self.turn_off() } else { Ok(()) };
+        self.disconnect();
+        turned_off
+    }
+
+    /// Fully restarts the connection: disconnects, re-applies every property set so far,
+    /// then runs `initialize` and `turn_on` again.
+    pub fn reconnect(&mut self) -> Result<(), LidarError> {
+        log_info!("reconnecting lidar");
+        self.disconnect();
+
+        let config = self.config.clone();
+        self.apply_config(&config)?;
+        self.initialize()?;
+        self.turn_on()
+    }
+
+    /// Calls `reconnect` up to `max_attempts` times, waiting `base_delay` before the first retry and doubling
+    /// the wait after every further failure. Returns the last error if every attempt fails.
+    pub fn reconnect_with_backoff(&mut self, max_attempts: usize, base_delay: Duration) -> Result<(), LidarError> {
+        let mut delay = base_delay;
+        let mut last_error = None;
+
+        for attempt in 0..max_attempts {
+            if attempt > 0 {
+                thread::sleep(delay);
+                delay *= 2;
+            }
+
+            match self.reconnect() {
+                Ok(()) => return Ok(()),
+                Err(e) => last_error = Some(e),
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| LidarError::with_kind(ErrorKind::InitFailed, "no reconnect attempts were made")))
+    }
+
+    /// Tries each of `AUTO_BAUD_RATES` in turn until `initialize` succeeds, and returns the rate that worked.
+    /// The lidar is disconnected after every failed attempt. Returns the last error if no rate works.
+    pub fn auto_baud(&mut self) -> Result<i32, LidarError> {
+        let mut last_error = None;
+
+        for rate in AUTO_BAUD_RATES {
+            self.set_property(LidarProperty::SerialBaudRate(rate))?;
+            match self.initialize() {
+                Ok(()) => return Ok(rate),
+                Err(e) => {
+                    log_debug!("no response at {} baud", rate);
+                    self.disconnect();
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| LidarError::with_kind(ErrorKind::InitFailed, "no baud rates to try")))
+    }
+
+    /// Firmware/hardware versions and serial number as reported during `initialize`. Can be called
+    /// before `turn_on`.
+    pub fn device_info(&self) -> Result<DeviceInfo, LidarError> {
+        let mut version = LidarVersion::default();
+        unsafe {
+            GetLidarVersion(self.lidar, &mut version as *mut _);
+        }
+
+        DeviceInfo::from_version(&version)
     }
 
+    /// The frequency the device is actually running at, measured from the duration of the most recent scan.
+    /// This can differ from the configured `ScanFrequency` depending on motor load.
+    pub fn current_scan_frequency(&self) -> Result<f32, LidarError> {
+        match self.last_scan_time {
+            Some(scan_time) if scan_time > 0.0 => Ok(1.0 / scan_time),
+            _ => Err(LidarError::with_kind(ErrorKind::QueryFailed, "no scan has been processed yet")),
+        }
+    }
+
+    /// Whether the lidar is currently scanning, i.e. `turn_on` succeeded and neither `turn_off` nor
+    /// `disconnect` has been called since. Tracked by the wrapper as the SDK's C API offers no such query.
+    pub fn is_scanning(&self) -> bool { self.scanning }
+
     pub fn turn_on(&mut self) -> Result<(), LidarError> {
         unsafe {
             if !turnOn(self.lidar) {
-                let error_description = CStr::from_ptr(DescribeError(self.lidar));
-                return Err(LidarError::new(error_description.to_str().unwrap()));
+                return Err(self.sdk_error(ErrorKind::TurnOnFailed));
             }
         }
 
+        self.scanning = true;
+        log_info!("lidar turned on");
         Ok(())
     }
 
     pub fn turn_off(&mut self) -> Result<(), LidarError> {
         unsafe {
             if !turnOff(self.lidar) {
-                let error_description = CStr::from_ptr(DescribeError(self.lidar));
-                return Err(LidarError::new(error_description.to_str().unwrap()));
+                return Err(self.sdk_error(ErrorKind::TurnOffFailed));
             }
         }
 
+        self.scanning = false;
+        log_info!("lidar turned off");
         Ok(())
     }
 
     pub fn do_process_simple(&mut self) -> Result<LaserScan, LidarError> {
-        let mut fan = LaserFan::default();
+        let mut scan = LaserScan::new(0, Vec::new());
+        self.do_process_into(&mut scan)?;
 
-        unsafe {
-            if !doProcessSimple(self.lidar, &mut fan as *mut _) {
-                let error_description = CStr::from_ptr(DescribeError(self.lidar));
-                return Err(LidarError::new(error_description.to_str().unwrap()));
+        Ok(scan)
+    }
+
+    /// Like `do_process_simple`, but refills `scan` in place, reusing the capacity of its points buffer.
+    /// On error the contents of `scan` are unspecified.
+    pub fn do_process_into(&mut self, scan: &mut LaserScan) -> Result<(), LidarError> {
+        self.acquire_into(scan, self.skip_invalid)?;
+        self.post_process(scan);
+
+        Ok(())
+    }
+
+    /// Iterates over successive scans until the lidar is turned off or more than
+    /// `DEFAULT_MAX_CONSECUTIVE_ERRORS` errors occur in a row (see `ScanIter::max_consecutive_errors`).
+    pub fn scans(&mut self) -> ScanIter<'_> {
+        ScanIter::new(self)
+    }
+
+    /// Collects fans until they cover a full 360° revolution and returns them as one scan stamped with the first
+    /// fan's timestamp. Points of the last fan past the completed revolution are dropped. Fails if more than the
+    /// configured maximum number of points (see `set_max_points`) arrive without completing a revolution.
+    pub fn scan_revolution(&mut self) -> Result<LaserScan, LidarError> {
+        let mut revolution = Revolution::default();
+        let mut fan = LaserScan::new(0, Vec::new());
+
+        loop {
+            self.acquire_into(&mut fan, self.skip_invalid)?;
+            if revolution.extend(&fan) {
+                let mut scan = revolution.into_scan();
+                self.post_process(&mut scan);
+                return Ok(scan);
             }
+
+            if revolution.points.len() > self.max_points {
+                return Err(LidarError::with_kind(ErrorKind::ProcessFailed, &format!("no full revolution after {} points", revolution.points.len())));
+            }
+        }
+    }
+
+    /// Like `do_process_simple`, but also returns the scan exactly as the SDK delivered it,
+    /// before the wrapper's own post-processing. Returns `(raw, processed)`.
+    pub fn do_process_both(&mut self) -> Result<(LaserScan, LaserScan), LidarError> {
+        let mut raw = LaserScan::new(0, Vec::new());
+        self.acquire_into(&mut raw, false)?;
+        let mut processed = raw.clone();
+        if self.skip_invalid {
+            processed.points.retain(|p| p.range > 0.0);
+        }
+        self.post_process(&mut processed);
+
+        Ok((raw, processed))
+    }
+
+    /*
+        Hook for transformations the wrapper applies on top of the SDK output. Nothing is
+        configured on a fresh handle, so processed and raw scans are identical by default.
+    */
+    fn post_process(&self, scan: &mut LaserScan) {
+        if self.phase_lock {
+            scan.sort_from_angle(PHASE_LOCK_REFERENCE_ANGLE);
+        }
+    }
+
+    /// Runs `f` on the points of the next fan exactly as the SDK delivered them, without copying them into
+    /// a `LaserScan`. The slice points into a buffer owned by the SDK and is only valid until `f` returns,
+    /// which the borrow enforces. Phase lock and `set_skip_invalid` don't apply here.
+    pub fn do_process_with<F, R>(&mut self, f: F) -> Result<R, LidarError>
+        where F: FnOnce(&[FfiLaserPoint]) -> R
+    {
+        let (fan, npoints) = self.process_fan()?;
+        let points = if npoints == 0 {
+            &[]
+        } else {
+            unsafe { std::slice::from_raw_parts(fan.points, npoints) }
         };
 
-        let npoints: usize = fan.npoints.try_into().unwrap();
-        let mut points = Vec::with_capacity(npoints);
+        Ok(f(points))
+    }
+
+    fn acquire_into(&mut self, scan: &mut LaserScan, skip_invalid: bool) -> Result<(), LidarError> {
+        let (fan, npoints) = self.process_fan()?;
 
-        for i in 0..fan.npoints.try_into().unwrap() {
+        scan.stamp = fan.stamp;
+        scan.points.clear();
+        scan.points.reserve(npoints);
+
+        for i in 0..npoints {
             let laser_point = unsafe {
                 let ffi_laser_point = *fan.points.add(i);
                 LaserPoint::new(ffi_laser_point.angle, ffi_laser_point.range, ffi_laser_point.intensity)
             };
-            points.push(laser_point);
+            if skip_invalid && laser_point.range == 0.0 {
+                continue;
+            }
+            scan.points.push(laser_point);
+        }
+
+        Ok(())
+    }
+
+    /*
+        Fetches the next fan from the SDK and returns it with its point count, checked against max_points.
+        A non-zero count is guaranteed to come with a non-null points pointer.
+    */
+    fn process_fan(&mut self) -> Result<(LaserFan, usize), LidarError> {
+        if !self.scanning {
+            return Err(LidarError::with_kind(ErrorKind::NotScanning, "lidar is not scanning, call turn_on first"));
+        }
+
+        let mut fan = LaserFan::default();
+
+        unsafe {
+            if !doProcessSimple(self.lidar, &mut fan as *mut _) {
+                return Err(self.sdk_error(ErrorKind::ProcessFailed));
+            }
+        };
+
+        self.last_scan_time = Some(fan.config.scan_time);
+
+        let npoints = checked_point_count(fan.npoints, self.max_points)?;
+        if npoints > 0 && fan.points.is_null() {
+            return Err(LidarError::with_kind(ErrorKind::ProcessFailed, "device returned point count but no data"));
+        }
+
+        Ok((fan, npoints))
+    }
+}
+
+/// Version of the YDLidar SDK this binary is linked against.
+pub fn sdk_version() -> String {
+    /*
+        GetSdkVersion copies a short "major.minor.patch" string without taking a buffer size.
+    */
+    let mut version = [0 as c_char; 64];
+    unsafe {
+        GetSdkVersion(version.as_mut_ptr());
+        CStr::from_ptr(version.as_ptr()).to_string_lossy().into_owned()
+    }
+}
+
+/// Formats `(start, end)` angle pairs, in degrees, into the comma-separated string expected by `LidarProperty::IgnoreArray`.
+pub fn ignore_ranges(pairs: &[(f32, f32)]) -> Result<String, LidarError> {
+    let mut values = Vec::with_capacity(pairs.len() * 2);
+    for &(start, end) in pairs {
+        if !(-180.0..=180.0).contains(&start) || !(-180.0..=180.0).contains(&end) {
+            return Err(LidarError::with_kind(ErrorKind::InvalidValue, &format!("ignore range {}..{} is outside -180..180 degrees", start, end)));
+        }
+        if start >= end {
+            return Err(LidarError::with_kind(ErrorKind::InvalidValue, &format!("ignore range {}..{} must start before it ends", start, end)));
+        }
+
+        values.push(start.to_string());
+        values.push(end.to_string());
+    }
+
+    Ok(values.join(","))
+}
+
+/// Lists the serial ports the SDK recognizes as YDLidar devices.
+pub fn list_ports() -> Vec<PortInfo> {
+    let mut ports = LidarPort::default();
+    let count = unsafe { lidarPortList(&mut ports as *mut _) };
+    let count = usize::try_from(count).unwrap_or(0).min(ports.port.len());
+
+    ports.port[..count].iter()
+        .map(|port| {
+            /*
+                The buffer is only NUL-terminated if the path is shorter than it, so don't use CStr here.
+            */
+            let bytes: Vec<u8> = port.data.iter().take_while(|&&c| c != 0).map(|&c| c as u8).collect();
+            PortInfo {
+                path: String::from_utf8_lossy(&bytes).into_owned(),
+            }
+        })
+        .collect()
+}
+
+/// A port found by `list_ports`. The SDK's C API only reports the device path, not the USB description.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PortInfo {
+    path: String,
+}
+
+impl PortInfo {
+    pub fn path(&self) -> &str { &self.path }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeviceInfo {
+    firmware_version: (u8, u8, u8),
+    hardware_version: u8,
+    serial_number: String,
+}
+
+impl DeviceInfo {
+    fn from_version(version: &LidarVersion) -> Result<Self, LidarError> {
+        /*
+            The SDK leaves the version zeroed until initialize has read it from the device.
+        */
+        if version.hardware == 0 && version.soft_major == 0 && version.soft_minor == 0 && version.soft_patch == 0 && version.sn.iter().all(|&d| d == 0) {
+            return Err(LidarError::with_kind(ErrorKind::QueryFailed, "device did not report version information, was initialize called?"));
+        }
+
+        Ok(Self {
+            firmware_version: (version.soft_major, version.soft_minor, version.soft_patch),
+            hardware_version: version.hardware,
+            serial_number: version.sn.iter().map(|digit| format!("{:X}", digit & 0x0f)).collect(),
+        })
+    }
+
+    /// (major, minor, patch)
+    pub fn firmware_version(&self) -> (u8, u8, u8) { self.firmware_version }
+    pub fn hardware_version(&self) -> u8 { self.hardware_version }
+    pub fn serial_number(&self) -> &str { &self.serial_number }
+}
+
+/*
+    The SDK makes no promises about the encoding of its error strings, so never panic on them.
+*/
+fn describe_error(error_description: &CStr) -> String {
+    error_description.to_string_lossy().into_owned()
+}
+
+/*
+    Device paths are handed to the SDK byte for byte, so on unix they don't need to be valid UTF-8.
+    Elsewhere the OS string has no portable byte form and must be UTF-8.
+*/
+fn path_to_cstring(path: &Path) -> Result<CString, LidarError> {
+    #[cfg(unix)]
+    let bytes = {
+        use std::os::unix::ffi::OsStrExt;
+        path.as_os_str().as_bytes()
+    };
+    #[cfg(not(unix))]
+    let bytes = path.to_str()
+        .ok_or_else(|| LidarError::with_kind(ErrorKind::InvalidValue, &format!("path is not valid UTF-8: {:?}", path)))?
+        .as_bytes();
+
+    CString::new(bytes)
+        .map_err(|_| LidarError::with_kind(ErrorKind::InvalidValue, &format!("path contains a NUL byte: {:?}", path)))
+}
+
+fn checked_point_count<T: TryInto<usize> + Copy + fmt::Display>(npoints: T, max_points: usize) -> Result<usize, LidarError> {
+    npoints.try_into()
+        .ok()
+        .filter(|&n| n <= max_points)
+        .ok_or_else(|| LidarError::with_kind(ErrorKind::ProcessFailed, &format!("device reported an invalid point count: {}", npoints)))
+}
+
+/*
+    Accumulates points while tracking how far the beam has swept, using the signed angular step between
+    consecutive points so that the ±π seam and either rotation direction are handled alike.
+*/
+#[derive(Default)]
+struct Revolution {
+    stamp: Option<u64>,
+    points: Vec<LaserPoint>,
+    previous_angle: Option<f32>,
+    swept: f32,
+}
+
+impl Revolution {
+    /*
+        Returns true once a full revolution has been collected.
+    */
+    fn extend(&mut self, fan: &LaserScan) -> bool {
+        self.stamp.get_or_insert(fan.stamp);
+
+        for point in &fan.points {
+            if let Some(previous) = self.previous_angle {
+                self.swept += normalize_angle(point.angle - previous);
+                /*
+                    Summing many small steps loses a little precision, so a point back at the
+                    starting angle can come out just short of 2π.
+                */
+                if self.swept.abs() >= TAU - 1e-4 {
+                    return true;
+                }
+            }
+
+            self.previous_angle = Some(point.angle);
+            self.points.push(*point);
+        }
+
+        false
+    }
+
+    fn into_scan(self) -> LaserScan {
+        LaserScan::new(self.stamp.unwrap_or(0), self.points)
+    }
+}
+
+pub const DEFAULT_MAX_POINTS: usize = 20_000;
+pub const DEFAULT_MAX_CONSECUTIVE_ERRORS: usize = 3;
+/// Baud rates tried by `Ydlidar::auto_baud`, in order.
+pub const AUTO_BAUD_RATES: [i32; 4] = [128000, 115200, 230400, 512000];
+
+pub struct ScanIter<'a, D: LidarDevice = Ydlidar> {
+    lidar: &'a mut D,
+    max_consecutive_errors: usize,
+    consecutive_errors: usize,
+}
+
+impl<'a, D: LidarDevice> ScanIter<'a, D> {
+    pub(crate) fn new(lidar: &'a mut D) -> Self {
+        Self {
+            lidar,
+            max_consecutive_errors: DEFAULT_MAX_CONSECUTIVE_ERRORS,
+            consecutive_errors: 0,
+        }
+    }
+
+    pub fn max_consecutive_errors(mut self, max: usize) -> Self {
+        self.max_consecutive_errors = max;
+        self
+    }
+}
+
+impl<D: LidarDevice> Iterator for ScanIter<'_, D> {
+    type Item = Result<LaserScan, LidarError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.lidar.is_scanning() || self.consecutive_errors > self.max_consecutive_errors {
+            return None;
+        }
+
+        let result = self.lidar.do_process_simple();
+        match result {
+            Ok(_) => self.consecutive_errors = 0,
+            Err(_) => self.consecutive_errors += 1,
         }
 
-        Ok(LaserScan::new(fan.stamp, points))
+        Some(result)
     }
 }
 
+impl Default for Ydlidar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl Drop for Ydlidar {
     fn drop(&mut self) {
-        unsafe { lidarDestroy(&mut self.lidar) }
+        if self.lidar.is_null() {
+            return;
+        }
+
+        /*
+            Stop the motor if the lidar was left running, e.g. when unwinding from a panic. After turn_off,
+            disconnect or shutdown 'scanning' is already false, so a handle that was shut down properly is
+            only destroyed. Errors can't be reported from here and are ignored.
+        */
+        unsafe {
+            if self.scanning {
+                turnOff(self.lidar);
+                disconnecting(self.lidar);
+            }
+            lidarDestroy(&mut self.lidar);
+        }
     }
 }
 
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LaserScan {
     stamp: u64,
     points: Vec<LaserPoint>,
@@ -196,58 +859,1136 @@ impl LaserScan {
     }
 
     pub fn stamp(&self) -> u64 { self.stamp }
-    pub fn points(&self) -> &Vec<LaserPoint> { &self.points }
-}
 
-#[derive(Copy, Clone)]
-pub struct LaserPoint {
-    angle: f32,
-    range: f32,
-    intensity: f32,
-}
+    /// The stamp in nanoseconds. Current SDKs take it from the system clock, i.e. nanoseconds since the Unix epoch.
+    pub fn timestamp_nanos(&self) -> u64 { self.stamp }
 
-impl LaserPoint {
-    pub(crate) fn new(angle: f32, range: f32, intensity: f32) -> Self {
-        Self {
-            angle,
-            range,
-            intensity,
+    /// The stamp as wall-clock time. Some SDK builds and firmware stamp scans with a monotonic clock (time since
+    /// boot) instead; such stamps predate the year 2000 when read as Unix time, and `None` is returned for them.
+    pub fn system_time(&self) -> Option<SystemTime> {
+        if self.stamp < WALL_CLOCK_MIN_STAMP {
+            return None;
         }
+
+        Some(UNIX_EPOCH + Duration::from_nanos(self.stamp))
     }
+    pub fn points(&self) -> &[LaserPoint] { &self.points }
 
-    pub fn angle(&self) -> f32 { self.angle }
-    pub fn range(&self) -> f32 { self.range }
-    pub fn intensity(&self) -> f32 { self.intensity }
-}
+    /// Summary statistics over the scan. Range and intensity figures only consider valid (non-zero range) points.
+    pub fn stats(&self) -> ScanStats {
+        let ranges: Vec<f32> = self.valid_points().map(|p| p.range).collect();
+        let intensities: Vec<f32> = self.valid_points().map(|p| p.intensity).collect();
 
+        ScanStats {
+            count: self.points.len(),
+            valid_count: ranges.len(),
+            range: Summary::of(&ranges),
+            intensity: Summary::of(&intensities),
+            intensities,
+        }
+    }
 
-pub enum LidarProperty<'a> {
-    SerialPort(&'a str),
-    IgnoreArray(&'a str),
-    SerialBaudRate(i32),
-    LidarType(i32),
-    DeviceType(i32),
-    SampleRate(i32),
-    AbnormalCheckCount(i32),
-    IntensityBit(i32),
-    MaxRange(f32),
-    MinRange(f32),
-    MaxAngle(f32),
-    MinAngle(f32),
-    ScanFrequency(f32),
-    FixedResolution(bool),
-    Reversion(bool),
-    Inverted(bool),
-    AutoReconnect(bool),
-    SingleChannel(bool),
-    Intensity(bool),
-    SupportMotorDtrCtrl(bool),
-    SupportHeartBeat(bool),
+    /// Histogram of the valid (non-zero) ranges, using `bins` equal-width bins spanning the
+    /// smallest to the largest range in the scan.
+    pub fn range_histogram(&self, bins: usize) -> Vec<usize> {
+        let ranges: Vec<f32> = self.valid_points().map(|p| p.range).collect();
+        histogram(&ranges, bins)
+    }
+
+    /// Shannon entropy (in bits) of the range histogram, i.e. of how the valid ranges spread between the
+    /// scan's own smallest and largest range. It is 0 when every range is the same and approaches
+    /// `log2(RANGE_ENTROPY_BINS)` when the ranges are spread evenly, regardless of the absolute distances:
+    /// a flat wall seen across a wide angle spreads its ranges too and scores high.
+    pub fn range_entropy(&self) -> f32 {
+        let counts = self.range_histogram(RANGE_ENTROPY_BINS);
+        let total: usize = counts.iter().sum();
+        if total == 0 {
+            return 0.0;
+        }
+
+        counts.iter()
+            .filter(|&&count| count > 0)
+            .map(|&count| {
+                let p = count as f32 / total as f32;
+                -p * p.log2()
+            })
+            .sum()
+    }
+
+    /// Renders the scan into a `size_px` x `size_px` RGBA buffer (row-major, 4 bytes per pixel) with the
+    /// sensor at the center, forward pointing up and `scale` pixels per meter. Points are colored by
+    /// intensity from blue (weakest in the scan) to red (strongest) on an opaque black background.
+    pub fn to_rgba_image(&self, size_px: u32, scale: f32) -> Vec<u8> {
+        let size = size_px as usize;
+        let mut image = vec![0; size * size * 4];
+        for pixel in image.chunks_exact_mut(4) {
+            pixel[3] = 255;
+        }
+
+        let max_intensity = self.valid_points().map(|p| p.intensity).fold(0.0, f32::max);
+        let center = size_px as f32 / 2.0;
+
+        for point in self.valid_points() {
+            let (x, y) = point.xy();
+            let col = (center - y * scale).floor();
+            let row = (center - x * scale).floor();
+            if col < 0.0 || row < 0.0 || col >= size_px as f32 || row >= size_px as f32 {
+                continue;
+            }
+
+            let t = if max_intensity > 0.0 { (point.intensity / max_intensity).clamp(0.0, 1.0) } else { 0.0 };
+            let offset = (row as usize * size + col as usize) * 4;
+            image[offset] = (t * 255.0) as u8;
+            image[offset + 1] = 0;
+            image[offset + 2] = ((1.0 - t) * 255.0) as u8;
+        }
+
+        image
+    }
+
+    /// Number of valid points in each quadrant, in the order front-left, front-right, back-left, back-right.
+    /// Forward is the sensor's 0 rad direction and left is positive angles; points on an axis count towards
+    /// the front/left side.
+    pub fn quadrant_counts(&self) -> [usize; 4] {
+        let mut counts = [0; 4];
+        for point in self.valid_points() {
+            let (x, y) = point.xy();
+            let index = match (x >= 0.0, y >= 0.0) {
+                (true, true) => 0,
+                (true, false) => 1,
+                (false, true) => 2,
+                (false, false) => 3,
+            };
+            counts[index] += 1;
+        }
+
+        counts
+    }
+
+    /// Copy of the scan containing only the points whose range lies within `[min, max]`.
+    pub fn filtered(&self, min: f32, max: f32) -> LaserScan {
+        let points = self.points.iter()
+            .filter(|p| p.range >= min && p.range <= max)
+            .copied()
+            .collect();
+
+        LaserScan::new(self.stamp, points)
+    }
+
+    /// Points within `half_width` radians of `center`, measured the short way around the circle so that
+    /// windows crossing the ±π seam behave like any other.
+    pub fn in_angle_window(&self, center: f32, half_width: f32) -> Vec<&LaserPoint> {
+        self.points.iter()
+            .filter(|p| normalize_angle(p.angle - center).abs() <= half_width)
+            .collect()
+    }
+
+    /// Cartesian `[x, y]` coordinates of every valid point, in meters.
+    pub fn to_point_cloud(&self) -> Vec<[f32; 2]> {
+        self.valid_points()
+            .map(|p| {
+                let (x, y) = p.xy();
+                [x, y]
+            })
+            .collect()
+    }
+
+    /// Like `to_point_cloud`, with every point placed at height `z`.
+    pub fn to_point_cloud_3d(&self, z: f32) -> Vec<[f32; 3]> {
+        self.valid_points()
+            .map(|p| {
+                let (x, y) = p.xy();
+                [x, y, z]
+            })
+            .collect()
+    }
+
+    /// Writes the scan as CSV: a `# stamp=<stamp>` comment line, an `angle,range,intensity` header and one row per point.
+    pub fn write_csv<W: Write>(&self, mut w: W) -> io::Result<()> {
+        writeln!(w, "# stamp={}", self.stamp)?;
+        writeln!(w, "angle,range,intensity")?;
+        for point in &self.points {
+            writeln!(w, "{},{},{}", point.angle, point.range, point.intensity)?;
+        }
+
+        w.flush()
+    }
+
+    /// Groups the points into angular buckets `bucket_rad` wide and keeps the strongest return of each, preferring
+    /// the nearest valid range when intensities are equal. The result is ordered by bucket, i.e. by ascending angle.
+    pub fn downsample_by_angle(&self, bucket_rad: f32) -> LaserScan {
+        if !(bucket_rad > 0.0 && bucket_rad.is_finite()) {
+            return self.clone();
+        }
+
+        let mut buckets: BTreeMap<i64, LaserPoint> = BTreeMap::new();
+        for point in &self.points {
+            let bucket = (point.angle / bucket_rad).floor() as i64;
+            buckets.entry(bucket)
+                .and_modify(|kept| {
+                    let nearer = point.range > 0.0 && (kept.range == 0.0 || point.range < kept.range);
+                    if point.intensity > kept.intensity || (point.intensity == kept.intensity && nearer) {
+                        *kept = *point;
+                    }
+                })
+                .or_insert(*point);
+        }
+
+        LaserScan::new(self.stamp, buckets.into_values().collect())
+    }
+
+    /// Ranges of all points, ordered by ascending angle.
+    pub fn ranges(&self) -> Vec<f32> {
+        let mut pairs: Vec<(f32, f32)> = self.points.iter().map(|p| (p.angle, p.range)).collect();
+        pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+        pairs.into_iter().map(|(_, range)| range).collect()
+    }
+
+    /// Bins the scan into `n` equal angular bins covering `[min, max)` radians and returns the nearest valid
+    /// range per bin. Bins without a return are 0.0, matching the SDK's convention for invalid points.
+    pub fn ranges_fixed(&self, n: usize, min: f32, max: f32) -> Vec<f32> {
+        let mut ranges = vec![0.0; n];
+        if n == 0 || max <= min {
+            return ranges;
+        }
+
+        let step = (max - min) / n as f32;
+        for point in self.valid_points() {
+            if point.angle < min || point.angle >= max {
+                continue;
+            }
+
+            let bin = (((point.angle - min) / step) as usize).min(n - 1);
+            if ranges[bin] == 0.0 || point.range < ranges[bin] {
+                ranges[bin] = point.range;
+            }
+        }
+
+        ranges
+    }
+
+    /// Median angle step, in radians, between consecutive points in angular order. `None` for fewer than two points.
+    pub fn angular_resolution(&self) -> Option<f32> {
+        let mut steps: Vec<f32> = self.sorted_angles().windows(2).map(|w| w[1] - w[0]).collect();
+        if steps.is_empty() {
+            return None;
+        }
+
+        /*
+            Both indices are the middle element for an odd count, and the two middle elements for an even one.
+        */
+        steps.sort_by(f32::total_cmp);
+        let len = steps.len();
+        Some((steps[(len - 1) / 2] + steps[len / 2]) / 2.0)
+    }
+
+    /// Angle ranges `(from, to)`, in radians, between neighbouring points that lie more than half a step
+    /// further apart than `expected_step`, i.e. where at least one point is missing. The gap across ±π is
+    /// not reported, since a scan need not cover a full revolution.
+    pub fn missing_sectors(&self, expected_step: f32) -> Vec<(f32, f32)> {
+        if expected_step.is_nan() || expected_step <= 0.0 {
+            return Vec::new();
+        }
+
+        self.sorted_angles()
+            .windows(2)
+            .filter(|w| w[1] - w[0] > expected_step * 1.5)
+            .map(|w| (w[0], w[1]))
+            .collect()
+    }
+
+    /// Sorts the points by ascending angle. NaN angles, which the SDK shouldn't produce, end up last.
+    pub fn sort_by_angle(&mut self) {
+        self.points.sort_by(|a, b| a.angle.total_cmp(&b.angle));
+    }
+
+    /// The angle of every point, in point order, remapped into [0, 2π).
+    pub fn normalized_angles(&self) -> Vec<f32> {
+        self.points.iter()
+            .map(|p| {
+                /*
+                    rem_euclid rounds tiny negative angles up to exactly 2π in f32.
+                */
+                let angle = p.angle.rem_euclid(TAU);
+                if angle >= TAU { 0.0 } else { angle }
+            })
+            .collect()
+    }
+
+    /*
+        Sorts the points by their angle normalized into [reference, reference + 2π).
+        total_cmp keeps the comparator well-defined should the SDK ever hand us a NaN angle.
+    */
+    fn sort_from_angle(&mut self, reference: f32) {
+        self.points.sort_by(|a, b| {
+            let a = (a.angle - reference).rem_euclid(TAU);
+            let b = (b.angle - reference).rem_euclid(TAU);
+            a.total_cmp(&b)
+        });
+    }
+
+    fn sorted_angles(&self) -> Vec<f32> {
+        let mut angles: Vec<f32> = self.points.iter().map(|p| p.angle).filter(|a| !a.is_nan()).collect();
+        angles.sort_by(f32::total_cmp);
+        angles
+    }
+
+    fn valid_points(&self) -> impl Iterator<Item=&LaserPoint> {
+        self.points.iter().filter(|p| p.range > 0.0)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScanStats {
+    pub count: usize,
+    pub valid_count: usize,
+    /// `None` if the scan has no valid points.
+    pub range: Option<Summary>,
+    /// `None` if the scan has no valid points.
+    pub intensity: Option<Summary>,
+    intensities: Vec<f32>,
+}
+
+impl ScanStats {
+    /// Histogram of the intensities of the valid points, using `bins` equal-width bins spanning
+    /// the smallest to the largest intensity.
+    pub fn intensity_histogram(&self, bins: usize) -> Vec<usize> {
+        histogram(&self.intensities, bins)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Summary {
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+}
+
+impl Summary {
+    fn of(values: &[f32]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+
+        let (min, max) = values.iter().fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &v| (min.min(v), max.max(v)));
+        let mean = values.iter().sum::<f32>() / values.len() as f32;
+        Some(Self { min, max, mean })
+    }
+}
+
+/*
+    Maps an angle into [-π, π).
+*/
+fn normalize_angle(angle: f32) -> f32 {
+    (angle + PI).rem_euclid(TAU) - PI
+}
+
+pub const RANGE_ENTROPY_BINS: usize = 32;
+/// 2000-01-01T00:00:00Z in nanoseconds since the Unix epoch.
+pub const WALL_CLOCK_MIN_STAMP: u64 = 946_684_800_000_000_000;
+pub const PHASE_LOCK_REFERENCE_ANGLE: f32 = 0.0;
+
+fn histogram(values: &[f32], bins: usize) -> Vec<usize> {
+    let mut counts = vec![0; bins];
+    if bins == 0 {
+        return counts;
+    }
+
+    let (min, max) = values.iter().fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &v| (min.min(v), max.max(v)));
+    let width = (max - min) / bins as f32;
+
+    for &v in values {
+        let bin = if width > 0.0 { ((v - min) / width) as usize } else { 0 };
+        counts[bin.min(bins - 1)] += 1;
+    }
+
+    counts
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LaserPoint {
+    angle: f32,
+    range: f32,
+    intensity: f32,
+}
+
+impl LaserPoint {
+    pub(crate) fn new(angle: f32, range: f32, intensity: f32) -> Self {
+        Self {
+            angle,
+            range,
+            intensity,
+        }
+    }
+
+    /// Angle in radians, as reported by the SDK (not degrees), counter-clockwise from the sensor's forward axis.
+    pub fn angle(&self) -> f32 { self.angle }
+    /// Range in meters. 0.0 means the beam produced no return.
+    pub fn range(&self) -> f32 { self.range }
+    pub fn intensity(&self) -> f32 { self.intensity }
+
+    /// Intensity as the integer the device reported. With `Intensity(true)` and `IntensityBit(n)` the valid
+    /// range is `0..=2^n - 1`; the SDK passes it on as an `f32`, which holds such values exactly.
+    pub fn intensity_raw(&self) -> u32 { self.intensity.max(0.0).round() as u32 }
+
+    pub fn x(&self) -> f32 { self.xy().0 }
+    pub fn y(&self) -> f32 { self.xy().1 }
+
+    /// Cartesian coordinates in meters, with x forward and y to the left. Points without a return map to (0.0, 0.0).
+    pub fn xy(&self) -> (f32, f32) {
+        if self.range == 0.0 {
+            return (0.0, 0.0);
+        }
+
+        (self.range * self.angle.cos(), self.range * self.angle.sin())
+    }
+}
+
+/// Scan frequency in Hz. Guaranteed to be finite and positive; whether the connected model supports the
+/// exact value is still checked by the SDK during `initialize`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ScanFrequency(f32);
+
+impl ScanFrequency {
+    pub fn new(hz: f32) -> Result<Self, LidarError> {
+        if !hz.is_finite() || hz <= 0.0 {
+            return Err(LidarError::with_kind(ErrorKind::InvalidValue, &format!("scan frequency must be a positive number of Hz, got {}", hz)));
+        }
+
+        Ok(Self(hz))
+    }
+
+    pub fn hz(&self) -> f32 { self.0 }
+}
+
+/// Measurement principle of the lidar, e.g. `Triangle` for the X/G series and `Tof` for the TG series.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LidarModelType {
+    Triangle,
+    Tof,
+    TofNet,
+}
+
+impl LidarModelType {
+    pub(crate) fn as_raw(self) -> i32 {
+        let raw = match self {
+            LidarModelType::Triangle => LidarTypeID_TYPE_TRIANGLE,
+            LidarModelType::Tof => LidarTypeID_TYPE_TOF,
+            LidarModelType::TofNet => LidarTypeID_TYPE_TOF_NET,
+        };
+        raw as i32
+    }
+}
+
+/// How the lidar is connected to the host. USB units show up as serial ports.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DeviceConnectionType {
+    Serial,
+    Tcp,
+    Udp,
+}
+
+impl DeviceConnectionType {
+    pub(crate) fn as_raw(self) -> i32 {
+        let raw = match self {
+            DeviceConnectionType::Serial => DeviceTypeID_YDLIDAR_TYPE_SERIAL,
+            DeviceConnectionType::Tcp => DeviceTypeID_YDLIDAR_TYPE_TCP,
+            DeviceConnectionType::Udp => DeviceTypeID_YDLIDAR_TYPE_UDP,
+        };
+        raw as i32
+    }
+}
+
+pub enum LidarProperty<'a> {
+    SerialPort(&'a str),
+    /// Same as `SerialPort`, for paths that may not be valid UTF-8.
+    SerialPortPath(&'a Path),
+    IgnoreArray(&'a str),
+    /// IP address of a network lidar such as the Ethernet variants of the TG series (`LidarModelType::TofNet`),
+    /// used together with `DeviceConnectionType::Tcp` or `Udp`. The SDK keeps it in the same slot as
+    /// `SerialPort`, so it is reported as `serial_port` by `dump_config`.
+    NetworkAddress(&'a str),
+    /// Port of a network lidar; the SDK keeps it in the same slot as `SerialBaudRate`, so it is reported
+    /// as `baud_rate` by `dump_config`.
+    NetworkPort(i32),
+    SerialBaudRate(i32),
+    LidarType(LidarModelType),
+    DeviceType(DeviceConnectionType),
+    SampleRate(i32),
+    AbnormalCheckCount(i32),
+    IntensityBit(i32),
+    MaxRange(f32),
+    MinRange(f32),
+    MaxAngle(f32),
+    MinAngle(f32),
+    ScanFrequency(ScanFrequency),
+    FixedResolution(bool),
+    Reversion(bool),
+    Inverted(bool),
+    AutoReconnect(bool),
+    SingleChannel(bool),
+    Intensity(bool),
+    SupportMotorDtrCtrl(bool),
+    SupportHeartBeat(bool),
+}
+
+impl LidarProperty<'_> {
+    /// The variant name, used to identify the property in error messages.
+    pub fn name(&self) -> &'static str {
+        match self {
+            LidarProperty::SerialPort(_) => "SerialPort",
+            LidarProperty::SerialPortPath(_) => "SerialPortPath",
+            LidarProperty::IgnoreArray(_) => "IgnoreArray",
+            LidarProperty::NetworkAddress(_) => "NetworkAddress",
+            LidarProperty::NetworkPort(_) => "NetworkPort",
+            LidarProperty::SerialBaudRate(_) => "SerialBaudRate",
+            LidarProperty::LidarType(_) => "LidarType",
+            LidarProperty::DeviceType(_) => "DeviceType",
+            LidarProperty::SampleRate(_) => "SampleRate",
+            LidarProperty::AbnormalCheckCount(_) => "AbnormalCheckCount",
+            LidarProperty::IntensityBit(_) => "IntensityBit",
+            LidarProperty::MaxRange(_) => "MaxRange",
+            LidarProperty::MinRange(_) => "MinRange",
+            LidarProperty::MaxAngle(_) => "MaxAngle",
+            LidarProperty::MinAngle(_) => "MinAngle",
+            LidarProperty::ScanFrequency(_) => "ScanFrequency",
+            LidarProperty::FixedResolution(_) => "FixedResolution",
+            LidarProperty::Reversion(_) => "Reversion",
+            LidarProperty::Inverted(_) => "Inverted",
+            LidarProperty::AutoReconnect(_) => "AutoReconnect",
+            LidarProperty::SingleChannel(_) => "SingleChannel",
+            LidarProperty::Intensity(_) => "Intensity",
+            LidarProperty::SupportMotorDtrCtrl(_) => "SupportMotorDtrCtrl",
+            LidarProperty::SupportHeartBeat(_) => "SupportHeartBeat",
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::path::PathBuf;
+
+    fn scan_with_ranges(ranges: &[f32]) -> LaserScan {
+        let points = ranges.iter()
+            .enumerate()
+            .map(|(i, &range)| LaserPoint::new(i as f32 * 0.01, range, 0.0))
+            .collect();
+        LaserScan::new(1, points)
+    }
+
+    #[test]
+    fn new_handle_assumes_every_capability() {
+        let mut lidar = Ydlidar::new();
+
+        assert_eq!(DeviceCapabilities::all(), lidar.capabilities());
+        assert!(lidar.set_property(LidarProperty::SupportHeartBeat(true)).is_ok());
+    }
+
+    #[test]
+    fn set_property_rejects_unsupported_features() {
+        let mut lidar = Ydlidar::new();
+        lidar.set_capabilities(DeviceCapabilities { motor_control: true, ..DeviceCapabilities::default() });
+
+        let err = lidar.set_property(LidarProperty::NetworkAddress("192.168.0.11")).unwrap_err();
+
+        assert_eq!(ErrorKind::Unsupported, err.kind);
+        assert!(lidar.set_property(LidarProperty::SupportMotorDtrCtrl(true)).is_ok());
+        assert!(lidar.set_property(LidarProperty::Intensity(false)).is_ok());
+    }
+
+    #[test]
+    fn apply_config_can_move_limits_past_the_current_ones() {
+        let mut lidar = Ydlidar::new();
+        lidar.set_property(LidarProperty::MaxRange(12.0)).unwrap();
+        lidar.set_property(LidarProperty::MinRange(0.1)).unwrap();
+
+        let config = LidarConfig {
+            max_range: Some(0.05),
+            min_range: Some(0.01),
+            ..LidarConfig::default()
+        };
+        lidar.apply_config(&config).unwrap();
+
+        assert_eq!(Some(0.05), lidar.dump_config().max_range);
+        assert_eq!(Some(0.01), lidar.dump_config().min_range);
+    }
+
+    #[test]
+    fn apply_config_rejects_inconsistent_limits() {
+        let mut lidar = Ydlidar::new();
+        let config = LidarConfig {
+            max_range: Some(1.0),
+            min_range: Some(5.0),
+            ..LidarConfig::default()
+        };
+
+        let err = lidar.apply_config(&config).unwrap_err();
+
+        assert_eq!(ErrorKind::InvalidValue, err.kind);
+        assert_eq!(None, lidar.dump_config().max_range);
+    }
+
+    #[test]
+    fn initialize_retry_without_attempts_fails() {
+        let mut lidar = Ydlidar::new();
+
+        assert_eq!(ErrorKind::InitFailed, lidar.initialize_retry(0, Duration::ZERO).unwrap_err().kind);
+    }
+
+    #[test]
+    fn do_process_with_requires_scanning() {
+        let mut lidar = Ydlidar::new();
+        let result = lidar.do_process_with(|points| points.len());
+
+        assert_eq!(ErrorKind::NotScanning, result.unwrap_err().kind);
+    }
+
+    #[test]
+    fn network_endpoint_shares_serial_slots() {
+        let mut lidar = Ydlidar::new();
+        lidar.set_property(LidarProperty::NetworkAddress("192.168.0.11")).unwrap();
+        lidar.set_property(LidarProperty::NetworkPort(8000)).unwrap();
+
+        assert_eq!(b"192.168.0.11", lidar.lidar_port.as_bytes());
+        let config = lidar.dump_config();
+        assert_eq!(Some(PathBuf::from("192.168.0.11")), config.serial_port);
+        assert_eq!(Some(8000), config.baud_rate);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn serial_port_path_keeps_non_utf8_bytes() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = Path::new(OsStr::from_bytes(b"/dev/tty\xffUSB0"));
+        let mut lidar = Ydlidar::new();
+        lidar.set_property(LidarProperty::SerialPortPath(path)).unwrap();
+
+        assert_eq!(b"/dev/tty\xffUSB0", lidar.lidar_port.as_bytes());
+        assert_eq!(Some(path.to_path_buf()), lidar.dump_config().serial_port);
+    }
+
+    #[test]
+    fn shutdown_of_idle_lidar_succeeds() {
+        let mut lidar = Ydlidar::new();
+
+        assert!(lidar.shutdown().is_ok());
+        assert!(!lidar.is_scanning());
+    }
+
+    #[test]
+    fn set_property_errors_name_the_property() {
+        let mut lidar = Ydlidar::new();
+        let err = lidar.set_property(LidarProperty::MinRange(-1.0)).unwrap_err();
+
+        assert_eq!(ErrorKind::InvalidValue, err.kind);
+        assert!(err.description.starts_with("failed to set MinRange: "), "{}", err.description);
+    }
+
+    #[test]
+    fn ignore_ranges_formats_pairs() {
+        assert_eq!("-10,10,90.5,120", ignore_ranges(&[(-10.0, 10.0), (90.5, 120.0)]).unwrap());
+        assert_eq!("", ignore_ranges(&[]).unwrap());
+    }
+
+    #[test]
+    fn ignore_ranges_rejects_reversed_or_out_of_bounds_pairs() {
+        assert_eq!(ErrorKind::InvalidValue, ignore_ranges(&[(10.0, -10.0)]).unwrap_err().kind);
+        assert_eq!(ErrorKind::InvalidValue, ignore_ranges(&[(5.0, 5.0)]).unwrap_err().kind);
+        assert_eq!(ErrorKind::InvalidValue, ignore_ranges(&[(-190.0, 0.0)]).unwrap_err().kind);
+        assert_eq!(ErrorKind::InvalidValue, ignore_ranges(&[(0.0, f32::NAN)]).unwrap_err().kind);
+    }
+
+    #[test]
+    fn device_info_formats_serial_number() {
+        let version = LidarVersion {
+            hardware: 1,
+            soft_major: 1,
+            soft_minor: 2,
+            soft_patch: 3,
+            sn: [2, 0, 2, 1, 0, 8, 1, 0, 0, 0, 0, 0, 0, 0, 4, 2],
+        };
+
+        let info = DeviceInfo::from_version(&version).unwrap();
+
+        assert_eq!((1, 2, 3), info.firmware_version());
+        assert_eq!(1, info.hardware_version());
+        assert_eq!("2021081000000042", info.serial_number());
+    }
+
+    #[test]
+    fn device_info_requires_reported_version() {
+        assert_eq!(ErrorKind::QueryFailed, DeviceInfo::from_version(&LidarVersion::default()).unwrap_err().kind);
+    }
+
+    #[test]
+    fn revolution_completes_across_fans_and_the_seam() {
+        let fan = |stamp: u64, start: i32| {
+            let points = (start..start + 10).map(|i| LaserPoint::new(normalize_angle(i as f32 * TAU / 24.0), 1.0, 0.0)).collect();
+            LaserScan::new(stamp, points)
+        };
+        let mut revolution = Revolution::default();
+
+        assert!(!revolution.extend(&fan(100, 6)));
+        assert!(!revolution.extend(&fan(200, 16)));
+        assert!(revolution.extend(&fan(300, 26)));
+
+        let scan = revolution.into_scan();
+        assert_eq!(100, scan.stamp());
+        assert_eq!(24, scan.points().len());
+    }
+
+    #[test]
+    fn checked_point_count_rejects_negative_and_oversized_counts() {
+        assert_eq!(720, checked_point_count(720u32, DEFAULT_MAX_POINTS).unwrap());
+        assert_eq!(ErrorKind::ProcessFailed, checked_point_count(-1i32, DEFAULT_MAX_POINTS).unwrap_err().kind);
+        assert_eq!(ErrorKind::ProcessFailed, checked_point_count(100_000u32, DEFAULT_MAX_POINTS).unwrap_err().kind);
+    }
+
+    #[test]
+    fn describe_error_tolerates_invalid_utf8() {
+        let description = c"timeout \xff";
+
+        assert_eq!("timeout \u{FFFD}", describe_error(description));
+    }
+
+    #[test]
+    fn string_property_with_nul_byte_is_rejected() {
+        let mut l = Ydlidar::new();
+
+        let result = l.set_property(LidarProperty::SerialPort("/dev/tty\0fake"));
+
+        assert_eq!(ErrorKind::InvalidValue, result.unwrap_err().kind);
+        assert_eq!(None, l.dump_config().serial_port);
+    }
+
+    #[test]
+    fn xy_converts_from_polar() {
+        let point = LaserPoint::new(std::f32::consts::FRAC_PI_2, 2.0, 0.0);
+
+        assert!(point.x().abs() < 1e-6);
+        assert!((point.y() - 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn intensity_raw_preserves_integer_value() {
+        assert_eq!(1023, LaserPoint::new(0.0, 1.0, 1023.0).intensity_raw());
+        assert_eq!(0, LaserPoint::new(0.0, 1.0, -1.0).intensity_raw());
+    }
+
+    #[test]
+    fn xy_of_invalid_point_is_origin() {
+        assert_eq!((0.0, 0.0), LaserPoint::new(f32::NAN, 0.0, 0.0).xy());
+    }
+
+    #[test]
+    fn apply_config_is_reflected_in_dump_config() {
+        let config = LidarConfig {
+            serial_port: Some(PathBuf::from("/dev/ttyUSB0")),
+            baud_rate: Some(230400),
+            lidar_type: Some(LidarModelType::Triangle),
+            scan_frequency: Some(10.0),
+            ..LidarConfig::default()
+        };
+        let mut l = Ydlidar::new();
+
+        l.apply_config(&config).unwrap();
+
+        assert_eq!(config, l.dump_config());
+    }
+
+    #[test]
+    fn reconnect_with_backoff_without_attempts_fails() {
+        let mut l = Ydlidar::new();
+
+        assert_eq!(ErrorKind::InitFailed, l.reconnect_with_backoff(0, Duration::from_millis(1)).unwrap_err().kind);
+    }
+
+    #[test]
+    fn multiple_lidars_are_independent() {
+        let mut first = Ydlidar::new();
+        let mut second = Ydlidar::new();
+
+        first.set_property(LidarProperty::SerialPort("/dev/ttyUSB0")).unwrap();
+        second.set_property(LidarProperty::SerialPort("/dev/ttyUSB1")).unwrap();
+        assert_eq!(Some(PathBuf::from("/dev/ttyUSB0")), first.dump_config().serial_port);
+        assert_eq!(Some(PathBuf::from("/dev/ttyUSB1")), second.dump_config().serial_port);
+
+        drop(first);
+
+        second.set_property(LidarProperty::SerialBaudRate(230400)).unwrap();
+        assert_eq!(Some(PathBuf::from("/dev/ttyUSB1")), second.dump_config().serial_port);
+        drop(second);
+    }
+
+    #[test]
+    fn from_raw_keeps_scanning_state() {
+        let ptr = Ydlidar::new().into_raw();
+        let mut l = unsafe { Ydlidar::from_raw_scanning(ptr) };
+
+        assert!(l.is_scanning());
+        l.disconnect();
+    }
+
+    #[test]
+    fn into_raw_leaves_handle_alive() {
+        let mut l = Ydlidar::new();
+        l.set_property(LidarProperty::SerialBaudRate(115200)).unwrap();
+
+        let ptr = l.into_raw();
+        assert!(!ptr.is_null());
+
+        let mut l = unsafe { Ydlidar::from_raw(ptr) };
+        l.set_property(LidarProperty::SerialPort("/dev/ttyUSB0")).unwrap();
+        assert_eq!(Some(PathBuf::from("/dev/ttyUSB0")), l.dump_config().serial_port);
+    }
+
+    #[test]
+    fn drop_of_null_handle_is_a_no_op() {
+        let l = unsafe { Ydlidar::from_raw_scanning(std::ptr::null_mut()) };
+
+        drop(l);
+    }
+
+    #[test]
+    fn into_raw_and_from_raw_round_trip() {
+        let l = Ydlidar::new();
+
+        let ptr = l.into_raw();
+        assert!(!ptr.is_null());
+
+        let l = unsafe { Ydlidar::from_raw(ptr) };
+        drop(l);
+    }
+
+    #[test]
+    fn current_scan_frequency_requires_a_scan() {
+        let l = Ydlidar::new();
+
+        assert_eq!(ErrorKind::QueryFailed, l.current_scan_frequency().unwrap_err().kind);
+    }
+
+    #[test]
+    fn do_process_simple_requires_scanning() {
+        let mut l = Ydlidar::new();
+
+        assert!(!l.is_scanning());
+        assert_eq!(ErrorKind::NotScanning, l.do_process_simple().unwrap_err().kind);
+    }
+
+    #[test]
+    fn scans_is_empty_when_not_scanning() {
+        let mut l = Ydlidar::new();
+
+        assert!(l.scans().next().is_none());
+    }
+
+    #[test]
+    fn lidar_error_displays_description() {
+        let error = LidarError::with_kind(ErrorKind::InitFailed, "connect error");
+        let boxed: Box<dyn Error> = Box::new(error);
+
+        assert_eq!("connect error", boxed.to_string());
+    }
+
+    #[test]
+    fn scan_frequency_rejects_nan_and_non_positive() {
+        assert_eq!(10.0, ScanFrequency::new(10.0).unwrap().hz());
+        assert_eq!(ErrorKind::InvalidValue, ScanFrequency::new(f32::NAN).unwrap_err().kind);
+        assert_eq!(ErrorKind::InvalidValue, ScanFrequency::new(0.0).unwrap_err().kind);
+        assert_eq!(ErrorKind::InvalidValue, ScanFrequency::new(-5.0).unwrap_err().kind);
+    }
+
+    #[test]
+    fn angular_resolution_is_median_step() {
+        let scan = LaserScan::new(1, vec![
+            LaserPoint::new(0.3, 1.0, 0.0),
+            LaserPoint::new(0.0, 1.0, 0.0),
+            LaserPoint::new(0.1, 1.0, 0.0),
+            LaserPoint::new(0.2, 1.0, 0.0),
+            LaserPoint::new(0.6, 1.0, 0.0),
+        ]);
+
+        assert!((scan.angular_resolution().unwrap() - 0.1).abs() < 1e-6);
+        let odd = LaserScan::new(1, vec![
+            LaserPoint::new(0.0, 1.0, 0.0),
+            LaserPoint::new(0.2, 1.0, 0.0),
+            LaserPoint::new(0.7, 1.0, 0.0),
+            LaserPoint::new(0.9, 1.0, 0.0),
+        ]);
+        assert!((odd.angular_resolution().unwrap() - 0.2).abs() < 1e-6);
+        assert_eq!(None, LaserScan::new(1, vec![LaserPoint::new(0.0, 1.0, 0.0)]).angular_resolution());
+    }
+
+    #[test]
+    fn missing_sectors_reports_gaps() {
+        let scan = LaserScan::new(1, vec![
+            LaserPoint::new(0.0, 1.0, 0.0),
+            LaserPoint::new(0.1, 1.0, 0.0),
+            LaserPoint::new(0.4, 1.0, 0.0),
+            LaserPoint::new(0.5, 1.0, 0.0),
+        ]);
+
+        assert_eq!(vec![(0.1, 0.4)], scan.missing_sectors(0.1));
+        assert!(scan.missing_sectors(0.0).is_empty());
+    }
+
+    #[test]
+    fn sort_by_angle_is_ascending_with_nan_last() {
+        let mut scan = LaserScan::new(1, vec![
+            LaserPoint::new(0.2, 1.0, 0.0),
+            LaserPoint::new(f32::NAN, 1.0, 0.0),
+            LaserPoint::new(-3.0, 1.0, 0.0),
+            LaserPoint::new(1.5, 1.0, 0.0),
+        ]);
+
+        scan.sort_by_angle();
+
+        let angles: Vec<f32> = scan.points().iter().map(|p| p.angle()).collect();
+        assert_eq!(vec![-3.0, 0.2, 1.5], angles[..3]);
+        assert!(angles[3].is_nan());
+    }
+
+    #[test]
+    fn normalized_angles_are_within_one_turn() {
+        let scan = LaserScan::new(1, vec![
+            LaserPoint::new(-PI / 2.0, 1.0, 0.0),
+            LaserPoint::new(0.5, 1.0, 0.0),
+            LaserPoint::new(TAU + 0.25, 1.0, 0.0),
+            LaserPoint::new(-1e-9, 1.0, 0.0),
+        ]);
+
+        let angles = scan.normalized_angles();
+
+        assert!((angles[0] - 3.0 * PI / 2.0).abs() < 1e-6);
+        assert_eq!(0.5, angles[1]);
+        assert!((angles[2] - 0.25).abs() < 1e-6);
+        assert!((0.0..TAU).contains(&angles[3]));
+    }
+
+    #[test]
+    fn sort_from_angle_starts_at_reference() {
+        let mut scan = LaserScan::new(1, vec![
+            LaserPoint::new(-0.5, 1.0, 0.0),
+            LaserPoint::new(0.2, 1.0, 0.0),
+            LaserPoint::new(3.0, 1.0, 0.0),
+            LaserPoint::new(-3.0, 1.0, 0.0),
+            LaserPoint::new(0.0, 1.0, 0.0),
+        ]);
+
+        scan.sort_from_angle(PHASE_LOCK_REFERENCE_ANGLE);
+
+        let angles: Vec<f32> = scan.points().iter().map(|p| p.angle()).collect();
+        assert_eq!(vec![0.0, 0.2, 3.0, -3.0, -0.5], angles);
+    }
+
+    #[test]
+    fn filtered_keeps_inclusive_range_and_stamp() {
+        let scan = scan_with_ranges(&[0.0, 0.1, 1.0, 12.0, 12.5]);
+
+        let filtered = scan.filtered(0.1, 12.0);
+
+        assert_eq!(scan.stamp(), filtered.stamp());
+        assert_eq!(vec![0.1, 1.0, 12.0], filtered.points().iter().map(|p| p.range()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn in_angle_window_handles_wraparound() {
+        let scan = LaserScan::new(1, vec![
+            LaserPoint::new(3.0, 1.0, 0.0),
+            LaserPoint::new(-3.0, 1.0, 0.0),
+            LaserPoint::new(2.5, 1.0, 0.0),
+            LaserPoint::new(0.0, 1.0, 0.0),
+        ]);
+
+        let window: Vec<f32> = scan.in_angle_window(PI, 0.3).iter().map(|p| p.angle()).collect();
+
+        assert_eq!(vec![3.0, -3.0], window);
+    }
+
+    #[test]
+    fn point_cloud_skips_invalid_points() {
+        let scan = LaserScan::new(1, vec![
+            LaserPoint::new(0.0, 2.0, 0.0),
+            LaserPoint::new(1.0, 0.0, 0.0),
+        ]);
+
+        assert_eq!(vec![[2.0, 0.0]], scan.to_point_cloud());
+        assert_eq!(vec![[2.0, 0.0, 0.5]], scan.to_point_cloud_3d(0.5));
+    }
+
+    #[test]
+    fn write_csv_writes_stamp_header_and_rows() {
+        let scan = LaserScan::new(42, vec![
+            LaserPoint::new(0.5, 1.25, 100.0),
+            LaserPoint::new(-0.5, 0.0, 0.0),
+        ]);
+
+        let mut buffer = Vec::new();
+        scan.write_csv(&mut buffer).unwrap();
+
+        assert_eq!("# stamp=42\nangle,range,intensity\n0.5,1.25,100\n-0.5,0,0\n", String::from_utf8(buffer).unwrap());
+    }
+
+    #[test]
+    fn downsample_by_angle_keeps_strongest_then_nearest() {
+        let scan = LaserScan::new(7, vec![
+            LaserPoint::new(0.05, 2.0, 10.0),
+            LaserPoint::new(0.06, 1.0, 30.0),
+            LaserPoint::new(0.15, 3.0, 5.0),
+            LaserPoint::new(0.16, 0.0, 5.0),
+            LaserPoint::new(0.17, 2.5, 5.0),
+            LaserPoint::new(-0.05, 4.0, 1.0),
+        ]);
+
+        let downsampled = scan.downsample_by_angle(0.1);
+
+        assert_eq!(7, downsampled.stamp());
+        assert_eq!(&[
+            LaserPoint::new(-0.05, 4.0, 1.0),
+            LaserPoint::new(0.06, 1.0, 30.0),
+            LaserPoint::new(0.17, 2.5, 5.0),
+        ], downsampled.points());
+    }
+
+    #[test]
+    fn ranges_are_sorted_by_angle() {
+        let scan = LaserScan::new(1, vec![
+            LaserPoint::new(0.5, 3.0, 0.0),
+            LaserPoint::new(-0.5, 1.0, 0.0),
+            LaserPoint::new(0.0, 2.0, 0.0),
+        ]);
+
+        assert_eq!(vec![1.0, 2.0, 3.0], scan.ranges());
+    }
+
+    #[test]
+    fn ranges_fixed_keeps_nearest_range_per_bin() {
+        let scan = LaserScan::new(1, vec![
+            LaserPoint::new(-0.9, 3.0, 0.0),
+            LaserPoint::new(-0.8, 2.0, 0.0),
+            LaserPoint::new(-0.7, 0.0, 0.0),
+            LaserPoint::new(0.5, 4.0, 0.0),
+            LaserPoint::new(1.5, 1.0, 0.0),
+        ]);
+
+        assert_eq!(vec![2.0, 0.0, 0.0, 4.0], scan.ranges_fixed(4, -1.0, 1.0));
+    }
+
+    #[test]
+    fn quadrant_counts_partitions_valid_points() {
+        let scan = LaserScan::new(1, vec![
+            LaserPoint::new(0.5, 1.0, 0.0),
+            LaserPoint::new(0.6, 1.0, 0.0),
+            LaserPoint::new(-0.5, 1.0, 0.0),
+            LaserPoint::new(2.5, 1.0, 0.0),
+            LaserPoint::new(-2.5, 1.0, 0.0),
+            LaserPoint::new(-2.5, 0.0, 0.0),
+        ]);
+
+        assert_eq!([2, 1, 1, 1], scan.quadrant_counts());
+    }
+
+    #[test]
+    fn to_rgba_image_plots_points_relative_to_center() {
+        let scan = LaserScan::new(1, vec![
+            LaserPoint::new(0.0, 1.0, 10.0),
+            LaserPoint::new(std::f32::consts::FRAC_PI_2, 1.0, 0.0),
+        ]);
+
+        let image = scan.to_rgba_image(10, 4.0);
+
+        assert_eq!(10 * 10 * 4, image.len());
+        let pixel = |row: usize, col: usize| &image[(row * 10 + col) * 4..(row * 10 + col) * 4 + 4];
+        assert_eq!(&[255, 0, 0, 255], pixel(1, 5));
+        assert_eq!(&[0, 0, 255, 255], pixel(5, 1));
+        assert_eq!(&[0, 0, 0, 255], pixel(5, 5));
+    }
+
+    #[test]
+    fn system_time_converts_wall_clock_stamps() {
+        let stamp = 1_600_000_000_123_456_789;
+        let scan = LaserScan::new(stamp, Vec::new());
+
+        assert_eq!(stamp, scan.timestamp_nanos());
+        assert_eq!(Some(UNIX_EPOCH + Duration::from_nanos(stamp)), scan.system_time());
+    }
+
+    #[test]
+    fn system_time_rejects_monotonic_stamps() {
+        let scan = LaserScan::new(3_600_000_000_000, Vec::new());
+
+        assert_eq!(None, scan.system_time());
+    }
+
+    #[test]
+    fn stats_summarize_valid_points() {
+        let scan = LaserScan::new(0, vec![
+            LaserPoint::new(0.0, 0.0, 90.0),
+            LaserPoint::new(0.1, 1.0, 10.0),
+            LaserPoint::new(0.2, 3.0, 30.0),
+            LaserPoint::new(0.3, 2.0, 20.0),
+        ]);
+
+        let stats = scan.stats();
+
+        assert_eq!(4, stats.count);
+        assert_eq!(3, stats.valid_count);
+        assert_eq!(Some(Summary { min: 1.0, max: 3.0, mean: 2.0 }), stats.range);
+        assert_eq!(Some(Summary { min: 10.0, max: 30.0, mean: 20.0 }), stats.intensity);
+        assert_eq!(vec![1, 1, 1], stats.intensity_histogram(3));
+    }
+
+    #[test]
+    fn stats_of_empty_scan_have_no_summaries() {
+        let stats = scan_with_ranges(&[0.0, 0.0]).stats();
+
+        assert_eq!(2, stats.count);
+        assert_eq!(0, stats.valid_count);
+        assert_eq!(None, stats.range);
+        assert_eq!(None, stats.intensity);
+        assert_eq!(vec![0, 0], stats.intensity_histogram(2));
+    }
+
+    #[test]
+    fn range_histogram_ignores_invalid_points() {
+        let scan = scan_with_ranges(&[0.0, 1.0, 1.5, 2.0, 0.0]);
+
+        assert_eq!(vec![1, 1, 1], scan.range_histogram(3));
+    }
+
+    #[test]
+    fn range_entropy_of_uniform_scan_is_zero() {
+        let scan = scan_with_ranges(&[2.0; 100]);
+
+        assert_eq!(0.0, scan.range_entropy());
+    }
+
+    #[test]
+    fn range_entropy_of_spread_scan_is_maximal() {
+        let ranges: Vec<f32> = (0..RANGE_ENTROPY_BINS).map(|i| 1.0 + i as f32).collect();
+        let scan = scan_with_ranges(&ranges);
+
+        let expected = (RANGE_ENTROPY_BINS as f32).log2();
+        assert!((scan.range_entropy() - expected).abs() < 1e-4);
+    }
+
+    #[test]
+    fn range_entropy_is_relative_to_the_scans_own_spread() {
+        let ranges: Vec<f32> = (0..100).map(|i| 1.0 + i as f32 * 0.01).collect();
+        let scaled: Vec<f32> = ranges.iter().map(|r| r * 10.0).collect();
+
+        let near = scan_with_ranges(&ranges).range_entropy();
+        let far = scan_with_ranges(&scaled).range_entropy();
+
+        assert!(near > 4.0);
+        assert!((near - far).abs() < 1e-4);
+    }
 
     #[test]
     fn start_lidar() {
@@ -256,8 +1997,8 @@ mod tests {
         l.set_property(LidarProperty::SerialPort("/dev/ydlidar")).unwrap();
         l.set_property(LidarProperty::IgnoreArray("")).unwrap();
         l.set_property(LidarProperty::SerialBaudRate(115200)).unwrap();
-        l.set_property(LidarProperty::LidarType(1)).unwrap();
-        l.set_property(LidarProperty::DeviceType(0)).unwrap();
+        l.set_property(LidarProperty::LidarType(LidarModelType::Triangle)).unwrap();
+        l.set_property(LidarProperty::DeviceType(DeviceConnectionType::Serial)).unwrap();
         l.set_property(LidarProperty::SampleRate(3)).unwrap();
         l.set_property(LidarProperty::AbnormalCheckCount(4)).unwrap();
         l.set_property(LidarProperty::FixedResolution(true)).unwrap();
@@ -271,7 +2012,7 @@ mod tests {
         l.set_property(LidarProperty::MinAngle(-180.0)).unwrap();
         l.set_property(LidarProperty::MaxRange(12.0)).unwrap();
         l.set_property(LidarProperty::MinRange(0.1)).unwrap();
-        l.set_property(LidarProperty::ScanFrequency(20.0)).unwrap();
+        l.set_property(LidarProperty::ScanFrequency(ScanFrequency::new(20.0).unwrap())).unwrap();
 
         l.initialize().unwrap();
 